@@ -0,0 +1,247 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    bot::{parse_bot_skills, BotSkill, UnknownBotSkill},
+    gym::{GymEnv, GymError, ResetOptions},
+    simulation::FIXED_TIMESTEP,
+    stats::MatchStats,
+    tuning::{Tuning, TuningLoaderError},
+    AppState,
+};
+
+const DEFAULT_MATCHES: u64 = 1000;
+const DEFAULT_MAX_MINUTES: f32 = 10.0;
+const DEFAULT_BOTS: [BotSkill; 2] = [BotSkill::Normal, BotSkill::Normal];
+// The simulation is only looked at between steps, so there is no need to stop every tick
+const TICKS_PER_STEP: u32 = 64;
+
+#[derive(Error, Debug)]
+pub enum BalanceError {
+    #[error(transparent)]
+    Tuning(#[from] TuningLoaderError),
+    #[error(transparent)]
+    UnknownSkill(#[from] UnknownBotSkill),
+    #[error("{flag} needs a number, got {value}")]
+    InvalidNumber { flag: &'static str, value: String },
+    #[error("Could not write the report: {0}")]
+    Report(#[from] std::io::Error),
+    #[error("Could not write the report: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Gym(#[from] GymError),
+}
+
+// Read from the command line:
+//   --balance [--matches 1000] [--seed 0] [--bots normal,hard]
+//             [--tuning my.tuning.ron] [--max-minutes 10] [--report report.json]
+#[derive(Debug)]
+struct BalanceOptions {
+    matches: u64,
+    first_seed: u64,
+    bots: Vec<BotSkill>,
+    tuning: Option<Tuning>,
+    max_minutes: f32,
+    report: Option<PathBuf>,
+}
+
+impl BalanceOptions {
+    fn from_args() -> Result<Self, BalanceError> {
+        let bots = match value_after("--bots") {
+            Some(bots) => parse_bot_skills(&bots)?,
+            None => DEFAULT_BOTS.to_vec(),
+        };
+        let tuning = value_after("--tuning").map(Tuning::load).transpose()?;
+
+        Ok(Self {
+            matches: number_after("--matches")?.unwrap_or(DEFAULT_MATCHES),
+            first_seed: number_after("--seed")?.unwrap_or(0),
+            bots,
+            tuning,
+            max_minutes: number_after("--max-minutes")?.unwrap_or(DEFAULT_MAX_MINUTES),
+            report: value_after("--report").map(PathBuf::from),
+        })
+    }
+}
+
+fn value_after(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    args.next()
+}
+
+fn number_after<T: std::str::FromStr>(flag: &'static str) -> Result<Option<T>, BalanceError> {
+    value_after(flag)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| BalanceError::InvalidNumber { flag, value })
+        })
+        .transpose()
+}
+
+// Spread of one measurement over all matches
+#[derive(Serialize, Debug, Default)]
+pub struct Distribution {
+    pub samples: usize,
+    pub mean: f32,
+    pub min: f32,
+    pub p10: f32,
+    pub median: f32,
+    pub p90: f32,
+    pub max: f32,
+}
+
+impl Distribution {
+    fn from_samples(mut samples: Vec<f32>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(f32::total_cmp);
+        let percentile = |fraction: f32| {
+            let index = ((samples.len() - 1) as f32 * fraction).round() as usize;
+            samples[index]
+        };
+
+        Self {
+            samples: samples.len(),
+            mean: samples.iter().sum::<f32>() / samples.len() as f32,
+            min: samples[0],
+            p10: percentile(0.1),
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct BalanceReport {
+    pub matches: u64,
+    pub bots: Vec<BotSkill>,
+    // Share of the matches that ended with every player down instead of running out of time
+    pub game_over_rate: f32,
+    pub match_seconds: Distribution,
+    pub lives_lost_per_minute: Distribution,
+    pub passes: Distribution,
+    // Pooled over every hit of every match
+    pub enemy_speed_at_hits: Distribution,
+}
+
+#[derive(Default)]
+struct Samples {
+    game_overs: u64,
+    match_seconds: Vec<f32>,
+    lives_lost_per_minute: Vec<f32>,
+    passes: Vec<f32>,
+    enemy_speed_at_hits: Vec<f32>,
+}
+
+impl Samples {
+    fn record(&mut self, stats: &MatchStats, game_over: bool) {
+        let seconds = stats.ticks_played as f32 * FIXED_TIMESTEP;
+        let lives_lost: u32 = stats.players.values().map(|player| player.lives_lost).sum();
+        let passes: u32 = stats.players.values().map(|player| player.passes).sum();
+
+        self.game_overs += game_over as u64;
+        self.match_seconds.push(seconds);
+        if seconds > 0.0 {
+            self.lives_lost_per_minute
+                .push(lives_lost as f32 / (seconds / 60.0));
+        }
+        self.passes.push(passes as f32);
+        self.enemy_speed_at_hits
+            .extend_from_slice(&stats.enemy_speed_at_hits);
+    }
+}
+
+// Plays seeded bot matches back to back and reports how they went, to tune
+// the game with numbers instead of guesses
+pub fn run() -> AppExit {
+    match simulate() {
+        Ok(()) => AppExit::Success,
+        Err(error) => {
+            eprintln!("{error}");
+            AppExit::error()
+        }
+    }
+}
+
+fn simulate() -> Result<(), BalanceError> {
+    let options = BalanceOptions::from_args()?;
+    let max_ticks = (options.max_minutes * 60.0 / FIXED_TIMESTEP) as u64;
+
+    let mut env = GymEnv::new();
+    let mut samples = Samples::default();
+    for index in 0..options.matches {
+        env.reset(ResetOptions {
+            seed: options.first_seed + index,
+            agents: 0,
+            bots: options.bots.clone(),
+            ticks_per_step: TICKS_PER_STEP,
+            max_ticks,
+            tuning: options.tuning.clone(),
+        });
+        while !env.step(&[])?.done {}
+
+        let world = env.world().expect("the environment was just reset");
+        let game_over = world.resource::<State<AppState>>().get() == &AppState::GameOver;
+        samples.record(world.resource::<MatchStats>(), game_over);
+
+        if (index + 1) % 100 == 0 {
+            eprintln!("{}/{} matches played", index + 1, options.matches);
+        }
+    }
+
+    let report = BalanceReport {
+        matches: options.matches,
+        bots: options.bots,
+        game_over_rate: samples.game_overs as f32 / options.matches.max(1) as f32,
+        match_seconds: Distribution::from_samples(samples.match_seconds),
+        lives_lost_per_minute: Distribution::from_samples(samples.lives_lost_per_minute),
+        passes: Distribution::from_samples(samples.passes),
+        enemy_speed_at_hits: Distribution::from_samples(samples.enemy_speed_at_hits),
+    };
+    print_report(&report);
+    if let Some(path) = options.report {
+        fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        println!("Report written to {}", path.display());
+    }
+    Ok(())
+}
+
+// The report is all that goes to stdout. Progress goes to stderr, and the
+// matches log nothing because the headless app has no `LogPlugin`
+fn print_report(report: &BalanceReport) {
+    println!();
+    println!(
+        "{} matches with {:?} bots, {:.0}% ended in a game over",
+        report.matches,
+        report.bots,
+        report.game_over_rate * 100.0
+    );
+    println!(
+        "{:<24} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "", "mean", "min", "p10", "median", "p90", "max"
+    );
+    for (name, distribution) in [
+        ("match length (s)", &report.match_seconds),
+        ("lives lost per minute", &report.lives_lost_per_minute),
+        ("passes", &report.passes),
+        ("enemy speed at hits", &report.enemy_speed_at_hits),
+    ] {
+        println!(
+            "{:<24} {:>8.1} {:>8.1} {:>8.1} {:>8.1} {:>8.1} {:>8.1}",
+            name,
+            distribution.mean,
+            distribution.min,
+            distribution.p10,
+            distribution.median,
+            distribution.p90,
+            distribution.max
+        );
+    }
+}
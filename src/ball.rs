@@ -1,26 +1,46 @@
 use crate::{
     direction_indicator::{spawn_indicator, DirectionIndicator},
     gamepad::PlayerAction,
-    player::Player,
+    input_buffer::{FixedTick, InputBuffer},
+    match_setup::MatchSetup,
+    player::{Player, PlayerDirection},
+    revive::Downed,
+    shield::Shield,
+    simulation::{GameplaySet, InterpolatedTranslation},
+    tuning::Tuning,
+    AppState,
 };
 
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 
+// Spawn the thrown ball far enough out that it clears the thrower's collider
+const THROW_OFFSET: f32 = 32.0;
+
 pub struct BallPlugin;
 
 impl Plugin for BallPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_ball)
-            .add_systems(Update, (snap_to_player, test_bug, return_ball));
+        app.add_event::<PossessionChanged>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                spawn_ball.in_set(MatchSetup::Entities),
+            )
+            .add_systems(Update, test_bug)
+            .add_systems(
+                FixedUpdate,
+                (snap_to_player, throw_ball, return_ball)
+                    .chain()
+                    .in_set(GameplaySet::Ball),
+            );
     }
 }
 fn test_bug(action_query: Query<(&ActionState<PlayerAction>, &Player)>) {
     // Iterate through each player to see if they jumped
     for (action_state, player) in action_query.iter() {
         if action_state.just_pressed(&PlayerAction::Throw) {
-            println!("Player {} dash!", player.gamepad.id);
+            info!("Player {} dash!", player.player_id);
         }
     }
 }
@@ -30,24 +50,39 @@ pub struct Ball {
     despawn_timer: f32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PossessionCause {
+    Catch,
+    Throw,
+    Steal,
+    Fumble,
+    Drop,
+}
+
+// Sent whenever the ball changes hands, `None` means the ball is loose
+#[derive(Event, Debug)]
+pub struct PossessionChanged {
+    pub from: Option<Entity>,
+    pub to: Option<Entity>,
+    pub cause: PossessionCause,
+}
+
 // #[derive(Component)]
 // pub struct BallHandler;
 
-// TODO - add ball sprite
-fn spawn_ball(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-) {
+fn spawn_ball(mut commands: Commands) {
+    spawn_loose_ball(&mut commands, Vec2::ZERO);
+}
+
+// Only the simulated part of the ball, `PresentationPlugin` gives it a mesh when there is a window.
+// The ball only lives as long as the match
+pub fn spawn_loose_ball(commands: &mut Commands, position: Vec2) -> Entity {
     let ball_entity = commands
         .spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(bevy::math::prelude::Circle::new(6.)).into(),
-                material: materials.add(ColorMaterial::from(Color::BLACK)),
-                transform: Transform::from_xyz(0.0, 0.0, 2.0),
-                ..default()
-            },
+            SpatialBundle::from_transform(Transform::from_xyz(position.x, position.y, 2.0)),
             Ball { despawn_timer: 4.0 },
+            InterpolatedTranslation::new(position.extend(2.0)),
+            StateScoped(AppState::InGame),
         ))
         .insert(RigidBody::Dynamic)
         .insert(Ccd::enabled())
@@ -57,6 +92,7 @@ fn spawn_ball(
         .id();
 
     info!("Spawned ball entity: {:?}", ball_entity);
+    ball_entity
 }
 
 //TODO: Not sure if this works correctly
@@ -64,16 +100,14 @@ fn snap_to_player(
     mut commands: Commands,
     mut event_reader: EventReader<CollisionEvent>,
     ball_query: Query<Entity, With<Ball>>,
-    mut players: Query<(Entity, &mut Player), With<KinematicCharacterController>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    mut players: Query<(Entity, &mut Player, &Shield), With<KinematicCharacterController>>,
     direction_indicator: Query<Entity, With<DirectionIndicator>>,
+    mut possession_changed: EventWriter<PossessionChanged>,
 ) {
-    if ball_query.is_empty() {
+    // There is no loose ball to catch while someone carries it
+    let Ok(ball) = ball_query.get_single() else {
         return;
-    }
-
-    let ball = ball_query.single();
+    };
 
     for event in event_reader.read() {
         if let CollisionEvent::Started(collider1, collider2, _event) = event {
@@ -84,35 +118,50 @@ fn snap_to_player(
 
             if ball == *collider1 || ball == *collider2 {
                 let mut old_ballhandler = None;
-                let mut new_ballhandler = false;
-                for (player_entity, mut player) in players.iter_mut() {
+                let mut new_ballhandler = None;
+                for (player_entity, mut player, shield) in players.iter_mut() {
                     if player.have_ball {
-                        old_ballhandler = Some(player);
+                        old_ballhandler = Some((player_entity, player));
+                        continue;
+                    }
+
+                    // A player holding up their shield can't catch
+                    if shield.is_raised() {
                         continue;
                     }
 
                     if player_entity == *collider2 || player_entity == *collider1 {
                         info!("Player entity involved in collision: {:?}", player_entity);
                         player.have_ball = true;
-                        new_ballhandler = true;
+                        new_ballhandler = Some(player_entity);
                         info!("BallHandler added to player");
                         commands.entity(ball).despawn();
                         info!("Ball removed");
 
                         info!("Adding direction indicator to new ballhandler");
-                        let direction_indicator =
-                            spawn_indicator(&mut commands, &mut meshes, &mut materials);
+                        let direction_indicator = spawn_indicator(&mut commands);
                         commands
                             .entity(player_entity)
                             .add_child(direction_indicator);
                     }
                 }
-                if old_ballhandler.is_some() && new_ballhandler {
-                    old_ballhandler.unwrap().have_ball = false;
-                    let direction_indicator = direction_indicator.get_single().unwrap();
-                    info!("Despawning direction indicator");
-                    commands.entity(direction_indicator).despawn();
+                let Some(new_ballhandler) = new_ballhandler else {
+                    continue;
+                };
+                let mut from = None;
+                if let Some((old_entity, mut old_player)) = old_ballhandler {
+                    old_player.have_ball = false;
+                    from = Some(old_entity);
+                    if let Ok(direction_indicator) = direction_indicator.get_single() {
+                        info!("Despawning direction indicator");
+                        commands.entity(direction_indicator).despawn();
+                    }
                 }
+                possession_changed.send(PossessionChanged {
+                    from,
+                    to: Some(new_ballhandler),
+                    cause: PossessionCause::Catch,
+                });
             }
         }
     }
@@ -120,66 +169,70 @@ fn snap_to_player(
 
 fn throw_ball(
     mut commands: Commands,
-    // ballhandler: Query<
-    //     (Entity, &ActionState<PlayerAction>, &Transform, &Player),
-    //     With<BallHandler>,
-    // >,
-    players: Query<(&ActionState<PlayerAction>, &Player)>,
-    indicator: Query<(&DirectionIndicator, &Transform)>,
-    // mut event_reader: EventReader<CollisionEvent>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    ball_query: Query<Entity, With<Ball>>,
+    mut players: Query<
+        (
+            Entity,
+            &Transform,
+            &PlayerDirection,
+            &mut Player,
+            &mut InputBuffer,
+        ),
+        Without<Downed>,
+    >,
+    indicators: Query<(Entity, &Parent, &DirectionIndicator)>,
+    mut possession_changed: EventWriter<PossessionChanged>,
+    tick: Res<FixedTick>,
+    tuning: Res<Tuning>,
 ) {
-    if indicator.is_empty() {
-        return;
-    }
+    for (player_entity, transform, player_direction, mut player, mut input_buffer) in
+        players.iter_mut()
+    {
+        if !player.have_ball {
+            continue;
+        }
+        // A throw pressed just before the catch is still buffered here,
+        // so the ball leaves again the moment it is caught
+        if !input_buffer.consume(
+            PlayerAction::Throw,
+            tick.0,
+            tuning.input_buffer.throw_window,
+        ) {
+            continue;
+        }
 
-    if !ball_query.is_empty() {
-        return;
-    }
+        let indicator = indicators
+            .iter()
+            .find(|(_, parent, _)| parent.get() == player_entity);
 
-    // let (ballhandler_entity, action, ballhandler_transform, player) = ballhandler.single();
-    let (indicator, indicator_transform) = indicator.get_single().unwrap();
-
-    for (action, player) in players.iter() {
-        if action.just_pressed(&PlayerAction::Throw) {
-            info!("player {:?} pressed throw", player);
-            // // Adjust for desired throwing power
-            // let impulse_strength = 500000.0;
-            // // Adjust density as needed
-            // let collider_mprops = ColliderMassProperties::Density(0.5);
-            //
-            // let move_direction = indicator.direction.normalize();
-            //
-            // //TODO: fix this, translation??????
-            // let ball = commands
-            //     .spawn((
-            //         MaterialMesh2dBundle {
-            //             mesh: meshes.add(shape::Circle::new(6.)).into(),
-            //             material: materials.add(ColorMaterial::from(Color::BLACK)),
-            //             transform: Transform::from_translation(indicator_transform.translation),
-            //             ..default()
-            //         },
-            //         Ball { despawn_timer: 4.0 },
-            //     ))
-            //     .insert(RigidBody::Dynamic)
-            //     .insert(Ccd::enabled())
-            //     .insert(Collider::ball(16.0))
-            //     .insert(collider_mprops)
-            //     .insert(ActiveEvents::COLLISION_EVENTS)
-            //     .insert(ExternalImpulse {
-            //         impulse: move_direction * impulse_strength,
-            //         torque_impulse: 0.0, // Optional: Set a torque impulse for spinning throw (default 0)
-            //     })
-            //     .id();
-            //
-            // info!("Spawned ball entity: {:?}", ball);
-        }
+        // Throw where the player aims, or where they last moved if they never aimed
+        let throw_direction = [
+            indicator.map_or(Vec2::ZERO, |(_, _, indicator)| indicator.direction),
+            player_direction.direction,
+        ]
+        .into_iter()
+        .find(|direction| *direction != Vec2::ZERO)
+        .unwrap_or(Vec2::Y)
+        .normalize();
 
-        if action.just_pressed(&PlayerAction::Dash) {
-            info!("player {:?} pressed dash", player);
+        info!("Player {} threw the ball", player.player_id);
+        player.have_ball = false;
+        if let Some((indicator_entity, _, _)) = indicator {
+            commands.entity(indicator_entity).despawn_recursive();
         }
+
+        let ball = spawn_loose_ball(
+            &mut commands,
+            transform.translation.truncate() + throw_direction * THROW_OFFSET,
+        );
+        commands
+            .entity(ball)
+            .insert(Velocity::linear(throw_direction * tuning.ball.throw_speed));
+
+        possession_changed.send(PossessionChanged {
+            from: Some(player_entity),
+            to: None,
+            cause: PossessionCause::Throw,
+        });
     }
 }
 
@@ -189,13 +242,12 @@ fn return_ball(
     mut ball_query: Query<(Entity, &mut Ball), With<Ball>>,
     time: Res<Time>,
 ) {
-    if ball_query.is_empty() {
+    let Ok((ball_enity, mut ball)) = ball_query.get_single_mut() else {
         return;
-    }
+    };
 
     for (player_entity, player, player_transform) in players.iter() {
         if player.have_ball {
-            let (ball_enity, mut ball) = ball_query.get_single_mut().unwrap();
             // info!("Ball timer: {:?}", ball.despawn_timer);
 
             ball.despawn_timer -= time.delta_seconds();
@@ -0,0 +1,468 @@
+use std::str::FromStr;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use leafwing_input_manager::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    ball::{Ball, PossessionChanged},
+    call_for_pass::CallingForPass,
+    enemy::Enemy,
+    gamepad::{next_player_id, PlayerAction},
+    input_buffer::{buffer_just_pressed, FixedInputSet, FixedTick, InputBuffer},
+    lobby::{ChangeLobbySetting, LobbySet, LobbySetting, SettingStep},
+    match_setup::MatchConfig,
+    player::{despawn_player, spawn_player, Player},
+    resources::{InputSource, JoinedPlayers},
+    revive::Downed,
+    rng::GameRng,
+    rounds::RoundMode,
+    AppState, GameState, BOTTOM_WALL, LEFT_WALL, RIGHT_WALL, TOP_WALL,
+};
+
+const MAX_BOTS: usize = 3;
+// Bots keep this far from the walls when looking for open space
+const WALL_MARGIN: f32 = 80.0;
+// Spots tried every time a bot looks for open space
+const OPEN_SPACE_CANDIDATES: usize = 12;
+// Close enough to the target spot to stop walking
+const ARRIVE_RADIUS: f32 = 24.0;
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BotSettings>()
+            .add_systems(
+                Update,
+                manage_lobby_bots
+                    .after(LobbySet)
+                    .run_if(in_state(AppState::Menu)),
+            )
+            // Bots decide right before the gameplay systems read their input, and
+            // before a replay records it, so playing it back presses the same buttons
+            .add_systems(
+                FixedPreUpdate,
+                drive_bots
+                    .in_set(FixedInputSet)
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotSkill {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl BotSkill {
+    pub fn next(self) -> Self {
+        match self {
+            BotSkill::Easy => BotSkill::Normal,
+            BotSkill::Normal => BotSkill::Hard,
+            BotSkill::Hard => BotSkill::Easy,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            BotSkill::Easy => BotSkill::Hard,
+            BotSkill::Normal => BotSkill::Easy,
+            BotSkill::Hard => BotSkill::Normal,
+        }
+    }
+
+    fn profile(self) -> SkillProfile {
+        match self {
+            BotSkill::Easy => SkillProfile {
+                reaction_ticks: 24,
+                aim_error: 0.35,
+                dodge_radius: 90.0,
+                dodge_chance: 0.3,
+                throw_delay_ticks: 96,
+            },
+            BotSkill::Normal => SkillProfile {
+                reaction_ticks: 12,
+                aim_error: 0.15,
+                dodge_radius: 140.0,
+                dodge_chance: 0.6,
+                throw_delay_ticks: 48,
+            },
+            BotSkill::Hard => SkillProfile {
+                reaction_ticks: 4,
+                aim_error: 0.03,
+                dodge_radius: 200.0,
+                dodge_chance: 0.95,
+                throw_delay_ticks: 16,
+            },
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("{0} is not a bot skill, use easy, normal or hard")]
+pub struct UnknownBotSkill(pub String);
+
+impl FromStr for BotSkill {
+    type Err = UnknownBotSkill;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "easy" => Ok(BotSkill::Easy),
+            "normal" => Ok(BotSkill::Normal),
+            "hard" => Ok(BotSkill::Hard),
+            _ => Err(UnknownBotSkill(name.to_string())),
+        }
+    }
+}
+
+// Reads a comma separated list like `easy,hard`, as passed to `--bots`
+pub fn parse_bot_skills(list: &str) -> Result<Vec<BotSkill>, UnknownBotSkill> {
+    list.split(',').map(str::parse).collect()
+}
+
+struct SkillProfile {
+    // Fixed ticks between two decisions
+    reaction_ticks: u64,
+    // Largest angle in radians a throw can be off by
+    aim_error: f32,
+    // How close the enemy gets before the bot tries to dash away
+    dodge_radius: f32,
+    dodge_chance: f64,
+    // Fixed ticks the bot holds on to the ball before passing it on
+    throw_delay_ticks: u64,
+}
+
+// The skill bots added from the lobby get
+#[derive(Resource, Debug, Default)]
+pub struct BotSettings {
+    pub skill: BotSkill,
+}
+
+// A player controlled by the computer. It writes to its `ActionState` the way
+// a device would, so the gameplay systems don't know the difference
+#[derive(Component, Debug)]
+pub struct Bot {
+    pub skill: BotSkill,
+    next_decision: u64,
+    target: Option<Vec2>,
+    caught_at: Option<u64>,
+}
+
+impl Bot {
+    pub fn new(skill: BotSkill) -> Self {
+        Self {
+            skill,
+            next_decision: 0,
+            target: None,
+            caught_at: None,
+        }
+    }
+}
+
+// Adds a bot of every skill in `skills`, for apps that start matches without the lobby
+pub fn add_bots(world: &mut World, skills: &[BotSkill]) {
+    for skill in skills.iter().copied() {
+        world.run_system_once(
+            move |mut commands: Commands,
+                  mut joined_players: ResMut<JoinedPlayers>,
+                  players: Query<&Player>| {
+                spawn_bot(&mut commands, &mut joined_players, &players, skill);
+            },
+        );
+    }
+}
+
+pub fn spawn_bot(
+    commands: &mut Commands,
+    joined_players: &mut ResMut<JoinedPlayers>,
+    players: &Query<&Player>,
+    skill: BotSkill,
+) -> Entity {
+    let bot_index = (0..)
+        .find(|index| !joined_players.0.contains_key(&InputSource::Bot(*index)))
+        .unwrap();
+    let input_source = InputSource::Bot(bot_index);
+
+    // An empty input map, only the bot presses anything
+    let player = spawn_player(
+        commands,
+        InputMap::default(),
+        input_source,
+        next_player_id(players),
+    );
+    commands.entity(player).insert(Bot::new(skill));
+    joined_players.0.insert(input_source, player);
+    player
+}
+
+// Stepping the bots setting adds a bot or removes the last one, stepping the
+// skill changes it for all of them
+fn manage_lobby_bots(
+    mut commands: Commands,
+    mut setting_changes: EventReader<ChangeLobbySetting>,
+    mut settings: ResMut<BotSettings>,
+    mut joined_players: ResMut<JoinedPlayers>,
+    players: Query<&Player>,
+    mut bots: Query<(Entity, &Player, &Transform, &mut Bot)>,
+    mut possession_changed: EventWriter<PossessionChanged>,
+) {
+    for change in setting_changes.read() {
+        match (change.setting, change.step) {
+            (LobbySetting::BotSkill, step) => {
+                settings.skill = match step {
+                    SettingStep::Previous => settings.skill.previous(),
+                    SettingStep::Next => settings.skill.next(),
+                };
+                for (_, _, _, mut bot) in bots.iter_mut() {
+                    bot.skill = settings.skill;
+                }
+                info!("Bots are now {:?}", settings.skill);
+            }
+            (LobbySetting::Bots, SettingStep::Next) => {
+                if bots.iter().count() >= MAX_BOTS {
+                    continue;
+                }
+                spawn_bot(&mut commands, &mut joined_players, &players, settings.skill);
+                info!("A {:?} bot has joined the game!", settings.skill);
+            }
+            (LobbySetting::Bots, SettingStep::Previous) => {
+                let Some((entity, player, transform, _)) =
+                    bots.iter().max_by_key(|(_, player, _, _)| player.player_id)
+                else {
+                    continue;
+                };
+                despawn_player(
+                    &mut commands,
+                    entity,
+                    player,
+                    transform.translation.truncate(),
+                    &mut joined_players,
+                    &mut possession_changed,
+                );
+                info!("Player {} has left the game!", player.player_id + 1);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn drive_bots(
+    mut bots: Query<
+        (
+            Entity,
+            &Player,
+            &Transform,
+            &mut Bot,
+            &mut ActionState<PlayerAction>,
+            &mut InputBuffer,
+        ),
+        Without<Downed>,
+    >,
+    players: Query<(Entity, &Player, &Transform), Without<Downed>>,
+    callers: Query<(), With<CallingForPass>>,
+    enemies: Query<&Transform, With<Enemy>>,
+    balls: Query<&Transform, With<Ball>>,
+    mut rng: ResMut<GameRng>,
+    tick: Res<FixedTick>,
+    config: Res<MatchConfig>,
+) {
+    // In free for all everyone else is a rival, bots neither pass nor ask for the ball
+    let co_op = config.rules.mode == RoundMode::CoOp;
+    let enemy_position = enemies
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+    let loose_ball = balls
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+    let carrier = players
+        .iter()
+        .find(|(_, player, _)| player.have_ball)
+        .map(|(entity, _, _)| entity);
+
+    for (entity, player, transform, mut bot, mut action_state, mut input_buffer) in bots.iter_mut()
+    {
+        let position = transform.translation.truncate();
+        let profile = bot.skill.profile();
+
+        // Button presses only last one tick, so the next decision can press them again
+        action_state.release(&PlayerAction::Throw);
+        action_state.release(&PlayerAction::Dash);
+        action_state.release(&PlayerAction::CallForPass);
+
+        match (player.have_ball, bot.caught_at) {
+            (true, None) => bot.caught_at = Some(tick.0),
+            (false, Some(_)) => bot.caught_at = None,
+            _ => {}
+        }
+
+        if tick.0 < bot.next_decision {
+            continue;
+        }
+        bot.next_decision = tick.0 + profile.reaction_ticks;
+
+        let enemy_distance = enemy_position.map(|enemy| enemy.distance(position));
+        if enemy_distance.is_some_and(|distance| distance < profile.dodge_radius)
+            // `gen_bool` panics outside 0 to 1, a typo in a profile shouldn't take the match down
+            && rng.ai.gen_bool(profile.dodge_chance.clamp(0.0, 1.0))
+        {
+            let away = enemy_position
+                .map(|enemy| (position - enemy).normalize_or_zero())
+                .filter(|away| *away != Vec2::ZERO)
+                .unwrap_or(Vec2::Y);
+            hold_axis(&mut action_state, PlayerAction::Move, away);
+            action_state.press(&PlayerAction::Dash);
+            bot.target = None;
+        } else if player.have_ball {
+            let ready_to_throw = bot
+                .caught_at
+                .is_some_and(|caught_at| tick.0 >= caught_at + profile.throw_delay_ticks);
+            let receiver =
+                safest_teammate(entity, &players, &callers, enemy_position).filter(|_| co_op);
+
+            match receiver {
+                Some(receiver) if ready_to_throw => {
+                    let error = rng.ai.gen_range(-profile.aim_error..=profile.aim_error);
+                    let direction =
+                        Vec2::from_angle(error).rotate((receiver - position).normalize_or_zero());
+                    hold_axis(&mut action_state, PlayerAction::Aim, direction);
+                    action_state.press(&PlayerAction::Throw);
+                }
+                _ => {
+                    bot.target = Some(open_space(
+                        position,
+                        bot.target,
+                        entity,
+                        &players,
+                        enemy_position,
+                        &mut rng,
+                    ));
+                }
+            }
+        } else if let Some(ball) =
+            loose_ball.filter(|ball| closest_player_to(*ball, &players) == Some(entity))
+        {
+            // Go for a loose ball only when nobody else is closer to it
+            bot.target = Some(ball);
+        } else {
+            // Ask for the ball once nobody is marking the bot
+            let open = !enemy_distance.is_some_and(|distance| distance < profile.dodge_radius);
+            if co_op && open && carrier.is_some_and(|carrier| carrier != entity) {
+                action_state.press(&PlayerAction::CallForPass);
+            }
+
+            // Get open for a pass
+            bot.target = Some(open_space(
+                position,
+                bot.target,
+                entity,
+                &players,
+                enemy_position,
+                &mut rng,
+            ));
+        }
+
+        match bot.target {
+            Some(target) if target.distance(position) > ARRIVE_RADIUS => {
+                hold_axis(
+                    &mut action_state,
+                    PlayerAction::Move,
+                    (target - position).normalize(),
+                );
+            }
+            Some(_) => action_state.release(&PlayerAction::Move),
+            None => {}
+        }
+
+        buffer_just_pressed(&action_state, &mut input_buffer, &tick);
+    }
+}
+
+fn hold_axis(action_state: &mut ActionState<PlayerAction>, action: PlayerAction, direction: Vec2) {
+    action_state.press(&action);
+    action_state.action_data_mut_or_default(&action).axis_pair =
+        Some(DualAxisData::from_xy(direction));
+}
+
+// The teammate furthest away from the enemy, out of the ones calling for the ball if anyone is
+fn safest_teammate(
+    thrower: Entity,
+    players: &Query<(Entity, &Player, &Transform), Without<Downed>>,
+    callers: &Query<(), With<CallingForPass>>,
+    enemy_position: Option<Vec2>,
+) -> Option<Vec2> {
+    let anyone_calling = players
+        .iter()
+        .any(|(entity, _, _)| entity != thrower && callers.contains(entity));
+
+    players
+        .iter()
+        .filter(|(entity, _, _)| *entity != thrower)
+        .filter(|(entity, _, _)| !anyone_calling || callers.contains(*entity))
+        .map(|(_, _, transform)| transform.translation.truncate())
+        .max_by(|a, b| {
+            let safety =
+                |position: &Vec2| enemy_position.map_or(0.0, |enemy| enemy.distance(*position));
+            safety(a).total_cmp(&safety(b))
+        })
+}
+
+fn closest_player_to(
+    point: Vec2,
+    players: &Query<(Entity, &Player, &Transform), Without<Downed>>,
+) -> Option<Entity> {
+    players
+        .iter()
+        .min_by(|(_, _, a), (_, _, b)| {
+            let distance = |transform: &Transform| transform.translation.truncate().distance(point);
+            distance(a).total_cmp(&distance(b))
+        })
+        .map(|(entity, _, _)| entity)
+}
+
+// Picks a spot far from the enemy that the other players aren't already standing on.
+// The current target is one of the candidates, so bots don't change their mind every decision
+fn open_space(
+    position: Vec2,
+    current_target: Option<Vec2>,
+    bot: Entity,
+    players: &Query<(Entity, &Player, &Transform), Without<Downed>>,
+    enemy_position: Option<Vec2>,
+    rng: &mut GameRng,
+) -> Vec2 {
+    let score = |spot: Vec2| {
+        let enemy_distance = enemy_position.map_or(0.0, |enemy| enemy.distance(spot).min(600.0));
+        let crowding = players
+            .iter()
+            .filter(|(entity, _, _)| *entity != bot)
+            .map(|(_, _, transform)| transform.translation.truncate().distance(spot))
+            .fold(300.0_f32, f32::min);
+        // Far away spots take long to reach
+        enemy_distance + 0.5 * crowding - 0.25 * position.distance(spot)
+    };
+
+    let candidates: Vec<Vec2> = (0..OPEN_SPACE_CANDIDATES)
+        .map(|_| {
+            Vec2::new(
+                rng.ai
+                    .gen_range(LEFT_WALL + WALL_MARGIN..RIGHT_WALL - WALL_MARGIN),
+                rng.ai
+                    .gen_range(BOTTOM_WALL + WALL_MARGIN..TOP_WALL - WALL_MARGIN),
+            )
+        })
+        .chain(current_target)
+        .collect();
+
+    candidates
+        .into_iter()
+        .max_by(|a, b| score(*a).total_cmp(&score(*b)))
+        .unwrap_or(position)
+}
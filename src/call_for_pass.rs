@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{gamepad::PlayerAction, player::Player, revive::Downed, simulation::GameplaySet};
+
+// How long a call for the ball lasts without pressing again
+const CALL_SECONDS: f32 = 2.0;
+
+pub struct CallForPassPlugin;
+
+impl Plugin for CallForPassPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (call_for_pass, expire_calls)
+                .chain()
+                .in_set(GameplaySet::Input),
+        );
+    }
+}
+
+// A player asking for the ball. Bots pass to players that call before anyone else
+#[derive(Component, Debug)]
+pub struct CallingForPass {
+    expires: Timer,
+}
+
+impl Default for CallingForPass {
+    fn default() -> Self {
+        Self {
+            expires: Timer::from_seconds(CALL_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+// Marks the child text that is shown while a player calls for the ball
+#[derive(Component)]
+pub struct CallText;
+
+pub fn spawn_call_text(parent: &mut ChildBuilder, asset_server: &Res<AssetServer>) {
+    parent.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "Pass!",
+                TextStyle {
+                    font: asset_server.load("fonts/JetBrainsMonoNerdFont-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ),
+            transform: Transform::from_xyz(0.0, 44.0, 0.2),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CallText,
+    ));
+}
+
+// Pressing again while calling starts the call over
+fn call_for_pass(
+    mut commands: Commands,
+    players: Query<(Entity, &Player, &ActionState<PlayerAction>), Without<Downed>>,
+) {
+    for (entity, player, action_state) in players.iter() {
+        if player.have_ball || !action_state.just_pressed(&PlayerAction::CallForPass) {
+            continue;
+        }
+
+        commands.entity(entity).insert(CallingForPass::default());
+    }
+}
+
+// A call ends once it runs out, the caller gets the ball or goes down
+fn expire_calls(
+    mut commands: Commands,
+    mut callers: Query<(Entity, &Player, &mut CallingForPass, Has<Downed>)>,
+    time: Res<Time>,
+) {
+    for (entity, player, mut call, downed) in callers.iter_mut() {
+        if call.expires.tick(time.delta()).finished() || player.have_ball || downed {
+            commands.entity(entity).remove::<CallingForPass>();
+        }
+    }
+}
+
+pub fn show_calls(
+    callers: Query<(), With<CallingForPass>>,
+    mut call_texts: Query<(&Parent, &mut Visibility), With<CallText>>,
+) {
+    for (parent, mut visibility) in call_texts.iter_mut() {
+        *visibility = if callers.contains(parent.get()) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
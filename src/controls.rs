@@ -0,0 +1,247 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use bevy::{prelude::*, utils::HashMap};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::gamepad::PlayerAction;
+
+const CONTROLS_CONFIG_PATH: &str = "controls.ron";
+
+// The actions that can be rebound from the controls screen, in the order they are listed
+pub const REMAPPABLE_ACTIONS: [PlayerAction; 7] = [
+    PlayerAction::Throw,
+    PlayerAction::Dash,
+    PlayerAction::Shield,
+    PlayerAction::Grab,
+    PlayerAction::CallForPass,
+    PlayerAction::Start,
+    PlayerAction::Disconnect,
+];
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ControlsConfig::load());
+    }
+}
+
+// Button bindings for one kind of controller, `None` leaves the action unbound
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GamepadBindings {
+    pub throw: Option<GamepadButtonType>,
+    pub dash: Option<GamepadButtonType>,
+    pub shield: Option<GamepadButtonType>,
+    pub grab: Option<GamepadButtonType>,
+    pub call_for_pass: Option<GamepadButtonType>,
+    pub start: Option<GamepadButtonType>,
+    pub disconnect: Option<GamepadButtonType>,
+    // Rumble strength from 0 (off) to 1
+    pub rumble: f32,
+}
+
+impl Default for GamepadBindings {
+    fn default() -> Self {
+        Self {
+            throw: Some(GamepadButtonType::RightTrigger2),
+            dash: Some(GamepadButtonType::South),
+            shield: Some(GamepadButtonType::LeftTrigger2),
+            grab: Some(GamepadButtonType::West),
+            call_for_pass: Some(GamepadButtonType::East),
+            start: Some(GamepadButtonType::Start),
+            disconnect: Some(GamepadButtonType::Select),
+            rumble: 1.0,
+        }
+    }
+}
+
+impl GamepadBindings {
+    pub fn get(&self, action: PlayerAction) -> Option<GamepadButtonType> {
+        match action {
+            PlayerAction::Throw => self.throw,
+            PlayerAction::Dash => self.dash,
+            PlayerAction::Shield => self.shield,
+            PlayerAction::Grab => self.grab,
+            PlayerAction::CallForPass => self.call_for_pass,
+            PlayerAction::Start => self.start,
+            PlayerAction::Disconnect => self.disconnect,
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, action: PlayerAction, button: Option<GamepadButtonType>) {
+        let binding = match action {
+            PlayerAction::Throw => &mut self.throw,
+            PlayerAction::Dash => &mut self.dash,
+            PlayerAction::Shield => &mut self.shield,
+            PlayerAction::Grab => &mut self.grab,
+            PlayerAction::CallForPass => &mut self.call_for_pass,
+            PlayerAction::Start => &mut self.start,
+            PlayerAction::Disconnect => &mut self.disconnect,
+            _ => return,
+        };
+        *binding = button;
+    }
+
+    pub fn input_map(&self, gamepad: Gamepad) -> InputMap<PlayerAction> {
+        let buttons = REMAPPABLE_ACTIONS
+            .iter()
+            .filter_map(|action| self.get(*action).map(|button| (*action, button)));
+
+        InputMap::default()
+            .insert_multiple(buttons)
+            .insert(PlayerAction::Move, DualAxis::left_stick())
+            .insert(PlayerAction::Aim, DualAxis::right_stick())
+            // Make sure to set the gamepad or all gamepads will be used!
+            .set_gamepad(gamepad)
+            .build()
+    }
+}
+
+// Bindings saved per controller name, so each kind of controller keeps its own layout
+#[derive(Resource, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ControlsConfig {
+    pub gamepads: HashMap<String, GamepadBindings>,
+}
+
+#[derive(Debug, Error)]
+pub enum ControlsConfigError {
+    #[error("Could not access controls config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse controls config: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("Could not write controls config: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+impl ControlsConfig {
+    // Falls back to the default bindings when there is no config file yet or it can't be read
+    pub fn load() -> Self {
+        Self::load_from(Path::new(CONTROLS_CONFIG_PATH))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        match Self::read(path) {
+            Ok(config) => config,
+            Err(ControlsConfigError::Io(error)) if error.kind() == ErrorKind::NotFound => {
+                Self::default()
+            }
+            Err(error) => {
+                warn!("{error}, using default controls");
+                Self::default()
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, ControlsConfigError> {
+        let contents = fs::read_to_string(path)?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    pub fn save(&self) -> Result<(), ControlsConfigError> {
+        self.save_to(Path::new(CONTROLS_CONFIG_PATH))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), ControlsConfigError> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    pub fn bindings_for(&self, gamepad_name: Option<&str>) -> GamepadBindings {
+        gamepad_name
+            .and_then(|name| self.gamepads.get(name))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    // A file of its own in the temp directory for every test, tests run in parallel
+    fn temp_config_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("birdstomp-{}-{name}.ron", std::process::id()))
+    }
+
+    #[test]
+    fn every_remappable_action_can_be_rebound() {
+        let mut bindings = GamepadBindings::default();
+        for action in REMAPPABLE_ACTIONS {
+            bindings.set(action, Some(GamepadButtonType::Mode));
+            assert_eq!(bindings.get(action), Some(GamepadButtonType::Mode));
+            bindings.set(action, None);
+            assert_eq!(bindings.get(action), None);
+        }
+    }
+
+    #[test]
+    fn saved_bindings_load_back_for_the_same_gamepad() {
+        let path = temp_config_path("round-trip");
+        let mut bindings = GamepadBindings::default();
+        bindings.set(PlayerAction::Throw, None);
+        bindings.set(PlayerAction::Dash, Some(GamepadButtonType::North));
+        bindings.rumble = 0.5;
+        let mut config = ControlsConfig::default();
+        config.gamepads.insert("Pad".to_string(), bindings.clone());
+
+        config.save_to(&path).unwrap();
+        let loaded = ControlsConfig::load_from(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.bindings_for(Some("Pad")), bindings);
+        assert_eq!(
+            loaded.bindings_for(Some("Other pad")),
+            GamepadBindings::default()
+        );
+        assert_eq!(loaded.bindings_for(None), GamepadBindings::default());
+    }
+
+    #[test]
+    fn a_missing_config_file_gives_the_default_bindings() {
+        let path = temp_config_path("missing");
+
+        assert!(matches!(
+            ControlsConfig::read(&path),
+            Err(ControlsConfigError::Io(error)) if error.kind() == ErrorKind::NotFound
+        ));
+        let config = ControlsConfig::load_from(&path);
+        assert!(config.gamepads.is_empty());
+        assert_eq!(config.bindings_for(Some("Pad")), GamepadBindings::default());
+    }
+
+    #[test]
+    fn a_corrupt_config_file_gives_the_default_bindings() {
+        let path = temp_config_path("corrupt");
+        fs::write(&path, "(gamepads: {\"Pad\": (throw: Some(").unwrap();
+
+        let read = ControlsConfig::read(&path);
+        let config = ControlsConfig::load_from(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(read, Err(ControlsConfigError::Parse(_))));
+        assert!(config.gamepads.is_empty());
+        assert_eq!(config.bindings_for(Some("Pad")), GamepadBindings::default());
+    }
+
+    #[test]
+    fn fields_missing_from_the_file_keep_their_defaults() {
+        let path = temp_config_path("partial");
+        fs::write(&path, "(gamepads: {\"Pad\": (dash: Some(North))})").unwrap();
+
+        let config = ControlsConfig::load_from(&path);
+        fs::remove_file(&path).unwrap();
+
+        let bindings = config.bindings_for(Some("Pad"));
+        assert_eq!(bindings.dash, Some(GamepadButtonType::North));
+        assert_eq!(bindings.throw, GamepadBindings::default().throw);
+        assert_eq!(bindings.rumble, 1.0);
+    }
+}
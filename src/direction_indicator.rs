@@ -1,19 +1,13 @@
-use bevy::{
-    prelude::*,
-    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
-};
+use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-use crate::{gamepad::PlayerAction, player::Player, GameState};
+use crate::{gamepad::PlayerAction, player::Player, simulation::GameplaySet};
 
 pub struct DirectionIndicatorPlugin;
 
 impl Plugin for DirectionIndicatorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (move_indicator).run_if(in_state(GameState::Running)),
-        );
+        app.add_systems(FixedUpdate, move_indicator.in_set(GameplaySet::Input));
     }
 }
 
@@ -54,19 +48,11 @@ pub fn move_indicator(
     }
 }
 
-pub fn spawn_indicator(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-) -> Entity {
-    let circle = Mesh2dHandle(meshes.add(Circle { radius: 4.0 }));
+pub fn spawn_indicator(commands: &mut Commands) -> Entity {
     commands
-        .spawn(MaterialMesh2dBundle {
-            mesh: circle,
-            material: materials.add(Color::srgb(255.0, 99.0, 71.0)),
-            transform: Transform::from_xyz(0.0, 30.0, 0.0),
-            ..Default::default()
-        })
+        .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+            0.0, 30.0, 0.0,
+        )))
         .insert(DirectionIndicator {
             direction: Vec2::new(0.0, 0.0),
         })
@@ -1,24 +1,34 @@
-use crate::{player::Player, AppState, GameState, LEFT_WALL, RIGHT_WALL, TOP_WALL};
+use crate::{
+    match_setup::MatchSetup,
+    player::{collision_with_enemy, Player, PlayerHit},
+    rng::GameRng,
+    simulation::{GameplaySet, InterpolatedTranslation},
+    tuning::Tuning,
+    AppState, LEFT_WALL, RIGHT_WALL, TOP_WALL,
+};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::Rng;
 
 use crate::sprites::{AnimationIndices, AnimationTimer};
 
-pub const INITIAL_SPEED: f32 = 400.0;
-
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_enemy)
-            // .add_systems(
-            //     FixedUpdate,
-            //     (move_enemy_toward_player)
-            //         .run_if(in_state(AppState::InGame))
-            //         .run_if(in_state(GameState::Running)),
-            // )
-            .add_systems(OnExit(AppState::InGame), despawn_enemy);
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            spawn_enemy.in_set(MatchSetup::Entities),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                move_enemy_toward_player.in_set(GameplaySet::Movement),
+                knock_back_after_hit
+                    .after(collision_with_enemy)
+                    .in_set(GameplaySet::Collision),
+            ),
+        );
     }
 }
 
@@ -32,46 +42,41 @@ pub struct Enemy {
     pub health: f32,
 }
 
-fn spawn_enemy(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases_layouts: ResMut<Assets<TextureAtlasLayout>>,
-) {
-    let mut rng = rand::thread_rng();
-    let texture = asset_server.load("monsters/tooth-walker/toothwalker-sheet.png");
-    // let texture_atlas =
-    // TextureAtlas::from_grid(texture_handle, Vec2::new(64.0, 64.0), 6, 5, None, None);
-    let layout = TextureAtlasLayout::from_grid(UVec2::new(64, 64), 6, 5, None, None);
-    let texture_atlas_layout = texture_atlases_layouts.add(layout);
+impl Enemy {
+    pub fn reset_speed(&mut self, tuning: &Tuning) {
+        self.speed = tuning.enemy.initial_speed;
+        self.current_speed = tuning.enemy.initial_speed;
+    }
+}
+
+// Somewhere along the top wall
+pub fn enemy_spawn_translation(rng: &mut GameRng) -> Vec3 {
+    Vec3::new(rng.spawns.gen_range(LEFT_WALL..RIGHT_WALL), TOP_WALL, 1.)
+}
+
+// Only the simulated part of the enemy, `PresentationPlugin` adds the sprite when there is a window.
+// Every match gets a fresh enemy, with the speed the tuning has at that point
+fn spawn_enemy(mut commands: Commands, mut rng: ResMut<GameRng>, tuning: Res<Tuning>) {
     // Use only the subset of sprites in the sheet that make up the run animation
     let animation_indices = AnimationIndices { first: 0, last: 5 };
+    let translation = enemy_spawn_translation(&mut rng);
 
     commands
         .spawn((
-            SpriteSheetBundle {
-                // texture_atlas: texture_atlas_handle,
-                // texture: texture_handle,
-                // sprite: Sprite::new(animation_indices.first),
-                //
-                texture,
-                atlas: TextureAtlas {
-                    layout: texture_atlas_layout,
-                    index: animation_indices.first,
-                },
-                transform: Transform {
-                    translation: Vec3::new(rng.gen_range(LEFT_WALL..RIGHT_WALL), TOP_WALL, 1.),
-                    scale: Vec3::splat(2.0),
-                    ..default()
-                },
+            SpatialBundle::from_transform(Transform {
+                translation,
+                scale: Vec3::splat(2.0),
                 ..default()
-            },
+            }),
             animation_indices,
             AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            InterpolatedTranslation::new(translation),
             Enemy {
-                speed: INITIAL_SPEED,
-                current_speed: INITIAL_SPEED,
+                speed: tuning.enemy.initial_speed,
+                current_speed: tuning.enemy.initial_speed,
                 health: 100.0,
             },
+            StateScoped(AppState::InGame),
         ))
         .insert(Ccd::enabled())
         .insert(RigidBody::Dynamic)
@@ -80,13 +85,16 @@ fn spawn_enemy(
 }
 
 fn move_enemy_toward_player(
-    mut enemy_query: Query<(&mut Transform, &mut Enemy)>,
+    mut enemy_query: Query<(&mut Transform, &mut Enemy), Without<Player>>,
     ballhandler_query: Query<(&Transform, &Player), With<Player>>,
     time: Res<Time>,
+    tuning: Res<Tuning>,
 ) {
     // let ballhandler_transform = ballhandler_query.single();
 
-    let (mut enemy_transform, mut enemy) = enemy_query.single_mut();
+    let Ok((mut enemy_transform, mut enemy)) = enemy_query.get_single_mut() else {
+        return;
+    };
 
     for (player_transform, player) in ballhandler_query.iter() {
         if player.have_ball {
@@ -109,13 +117,31 @@ fn move_enemy_toward_player(
             }
 
             enemy_transform.translation += direction * enemy.speed * time.delta_seconds();
-            enemy.current_speed += 0.8;
+            enemy.current_speed += tuning.enemy.speed_ramp * time.delta_seconds();
         }
     }
 }
 
-fn despawn_enemy(mut commands: Commands, enemy_query: Query<Entity, With<Enemy>>) {
-    for enemy in enemy_query.iter() {
-        commands.entity(enemy).despawn_recursive();
+// Pushes the enemy off the player it hit, like a blocked hit does
+fn knock_back_after_hit(
+    mut player_hit: EventReader<PlayerHit>,
+    players: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut enemies: Query<&mut Transform, With<Enemy>>,
+    tuning: Res<Tuning>,
+) {
+    let Ok(mut enemy_transform) = enemies.get_single_mut() else {
+        return;
+    };
+
+    for event in player_hit.read() {
+        let Ok(player_transform) = players.get(event.player) else {
+            continue;
+        };
+
+        let push_direction = (enemy_transform.translation - player_transform.translation)
+            .truncate()
+            .try_normalize()
+            .unwrap_or(Vec2::Y);
+        enemy_transform.translation += (push_direction * tuning.enemy.hit_knockback).extend(0.0);
     }
 }
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use bevy::{
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{
+    ball::{PossessionCause, PossessionChanged},
+    enemy::Enemy,
+    player::{Player, PlayerHit},
+    simulation::CountdownAdvanced,
+    AppState,
+};
+
+// The enemy passing closer than this without touching counts as a near miss
+const NEAR_MISS_RADIUS: f32 = 50.0;
+
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Feedback>()
+            // Near misses are judged on simulated positions, after the physics step
+            .add_systems(
+                FixedPostUpdate,
+                near_miss_feedback.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    (catch_feedback, hit_feedback, countdown_feedback)
+                        .run_if(in_state(AppState::InGame)),
+                    rumble_on_feedback,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackKind {
+    Catch,
+    Hit,
+    NearMiss,
+    CountdownGo,
+}
+
+// Something happened in the game that a player should feel.
+// `player` is `None` when the feedback is meant for everyone
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Feedback {
+    pub player: Option<Entity>,
+    pub kind: FeedbackKind,
+}
+
+// How strong rumble is for a player, from 0 (off) to 1
+#[derive(Component, Debug, Clone, Copy)]
+pub struct RumbleIntensity(pub f32);
+
+impl Default for RumbleIntensity {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+// The rumble `gamepad` gets for `kind`, scaled by the player's rumble setting.
+// Nothing when rumble is turned off
+pub fn rumble_for(
+    kind: FeedbackKind,
+    gamepad: Gamepad,
+    rumble_intensity: RumbleIntensity,
+) -> Option<GamepadRumbleRequest> {
+    if rumble_intensity.0 <= 0.0 {
+        return None;
+    }
+
+    let (pattern, duration) = rumble_pattern(kind);
+    Some(GamepadRumbleRequest::Add {
+        gamepad,
+        duration,
+        intensity: GamepadRumbleIntensity {
+            strong_motor: pattern.strong_motor * rumble_intensity.0,
+            weak_motor: pattern.weak_motor * rumble_intensity.0,
+        },
+    })
+}
+
+fn rumble_pattern(kind: FeedbackKind) -> (GamepadRumbleIntensity, Duration) {
+    match kind {
+        FeedbackKind::Catch => (
+            GamepadRumbleIntensity::weak_motor(0.4),
+            Duration::from_millis(100),
+        ),
+        FeedbackKind::Hit => (GamepadRumbleIntensity::MAX, Duration::from_millis(300)),
+        FeedbackKind::NearMiss => (
+            GamepadRumbleIntensity::weak_motor(0.6),
+            Duration::from_millis(150),
+        ),
+        FeedbackKind::CountdownGo => (
+            GamepadRumbleIntensity {
+                strong_motor: 0.5,
+                weak_motor: 0.5,
+            },
+            Duration::from_millis(200),
+        ),
+    }
+}
+
+fn catch_feedback(
+    mut possession_changed: EventReader<PossessionChanged>,
+    mut feedback: EventWriter<Feedback>,
+) {
+    for event in possession_changed.read() {
+        if let (PossessionCause::Catch, Some(receiver)) = (event.cause, event.to) {
+            feedback.send(Feedback {
+                player: Some(receiver),
+                kind: FeedbackKind::Catch,
+            });
+        }
+    }
+}
+
+fn hit_feedback(mut player_hit: EventReader<PlayerHit>, mut feedback: EventWriter<Feedback>) {
+    for event in player_hit.read() {
+        feedback.send(Feedback {
+            player: Some(event.player),
+            kind: FeedbackKind::Hit,
+        });
+    }
+}
+
+// The last step of the start countdown is when play begins
+fn countdown_feedback(
+    mut countdown_advanced: EventReader<CountdownAdvanced>,
+    mut feedback: EventWriter<Feedback>,
+) {
+    for CountdownAdvanced(duration) in countdown_advanced.read() {
+        if *duration == 1 {
+            feedback.send(Feedback {
+                player: None,
+                kind: FeedbackKind::CountdownGo,
+            });
+        }
+    }
+}
+
+fn near_miss_feedback(
+    players: Query<(Entity, &Transform, &Player)>,
+    enemies: Query<&Transform, With<Enemy>>,
+    mut feedback: EventWriter<Feedback>,
+    // Players the enemy is currently passing, with the lives they had when it got close
+    mut passes: Local<HashMap<Entity, u32>>,
+) {
+    for (player_entity, player_transform, player) in players.iter() {
+        let is_near = enemies.iter().any(|enemy_transform| {
+            enemy_transform
+                .translation
+                .truncate()
+                .distance(player_transform.translation.truncate())
+                <= NEAR_MISS_RADIUS
+        });
+
+        if is_near {
+            passes.entry(player_entity).or_insert(player.lives);
+            continue;
+        }
+
+        // The enemy moved away again, it only counts as a near miss if it didn't land a hit
+        if passes.remove(&player_entity) == Some(player.lives) {
+            feedback.send(Feedback {
+                player: Some(player_entity),
+                kind: FeedbackKind::NearMiss,
+            });
+        }
+    }
+}
+
+fn rumble_on_feedback(
+    mut feedback: EventReader<Feedback>,
+    players: Query<(Entity, &Player, &RumbleIntensity)>,
+    mut rumble_requests: EventWriter<GamepadRumbleRequest>,
+) {
+    for event in feedback.read() {
+        for (player_entity, player, rumble_intensity) in players.iter() {
+            if event.player.is_some_and(|target| target != player_entity) {
+                continue;
+            }
+            let Some(gamepad) = player.gamepad() else {
+                continue;
+            };
+
+            if let Some(request) = rumble_for(event.kind, gamepad, *rumble_intensity) {
+                rumble_requests.send(request);
+            }
+        }
+    }
+}
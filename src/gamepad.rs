@@ -1,20 +1,59 @@
+use std::time::Duration;
+
 use crate::{
-    player::{spawn_player, Player},
-    resources::JoinedPlayers,
+    ball::PossessionChanged,
+    controls::ControlsConfig,
+    feedback::RumbleIntensity,
+    player::{despawn_player, spawn_player, Player},
+    resources::{CountdownTimer, InputSource, JoinedPlayers, KeyboardLayout, PauseMenu},
+    AppState, GameState,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, window::PrimaryWindow};
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct GamepadPlugin;
 
+// Disconnecting needs a long press so it can't happen by accident mid-match
+const DISCONNECT_HOLD: Duration = Duration::from_secs(1);
+
 impl Plugin for GamepadPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(InputManagerPlugin::<PlayerAction>::default())
-            .add_systems(Update, (join, disconnect));
+            .add_systems(
+                PreUpdate,
+                (aim_with_mouse, aim_with_keys).in_set(InputManagerSystem::ManualControl),
+            )
+            .add_systems(Update, (join, join_keyboard, disconnect))
+            .add_systems(
+                Update,
+                (
+                    handle_gamepad_connections,
+                    leave_disconnected_players.run_if(in_state(AppState::InGame)),
+                    // The pause menu runs the countdown itself once it is closed
+                    resume_after_reconnect
+                        .run_if(in_state(AppState::InGame))
+                        .run_if(not(resource_exists::<PauseMenu>)),
+                )
+                    .chain(),
+            );
     }
 }
 
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+#[derive(
+    Actionlike,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Debug,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
 pub enum PlayerAction {
     Up,
     Down,
@@ -22,22 +61,42 @@ pub enum PlayerAction {
     Right,
     Throw,
     Dash,
+    Shield,
+    Grab,
+    CallForPass,
     Move,
     Aim,
     Start,
     Disconnect,
 }
 
+// Players whose controller dropped out mid-match, kept around until it comes back or they leave
+#[derive(Component)]
+pub struct AwaitingReconnect;
+
+// Players that aim towards the mouse cursor instead of a stick
+#[derive(Component)]
+pub struct MouseAim;
+
+// Players that aim with their movement keys instead of a stick
+#[derive(Component)]
+pub struct KeyAim;
+
+// Returns the lowest player id that is not taken by a joined player
+pub fn next_player_id(players: &Query<&Player>) -> usize {
+    (0..)
+        .find(|id| players.iter().all(|player| player.player_id != *id))
+        .unwrap()
+}
+
 fn join(
     mut commands: Commands,
     mut joined_players: ResMut<JoinedPlayers>,
     gamepads: Res<Gamepads>,
     button_inputs: Res<ButtonInput<GamepadButton>>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
-    asset_server: Res<AssetServer>,
     // scene_assets: Res<SceneAssets>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<&Player>,
+    controls_config: Res<ControlsConfig>,
 ) {
     for gamepad in gamepads.iter() {
         // Join the game when both bumpers (L+R) on the controller are pressed
@@ -46,54 +105,344 @@ fn join(
             && button_inputs.pressed(GamepadButton::new(gamepad, GamepadButtonType::RightTrigger))
         {
             // Make sure a player can not join twice
-            if !joined_players.0.contains_key(&gamepad) {
-                println!("Player {} has joined the game!", gamepad.id);
-
-                let input_map = InputMap::default()
-                    .insert_multiple([
-                        (PlayerAction::Throw, GamepadButtonType::RightTrigger2),
-                        (PlayerAction::Dash, GamepadButtonType::South),
-                        (PlayerAction::Start, GamepadButtonType::Start),
-                        (PlayerAction::Disconnect, GamepadButtonType::Select),
-                    ])
-                    .insert(PlayerAction::Move, DualAxis::left_stick())
-                    .insert(PlayerAction::Aim, DualAxis::right_stick())
-                    // Make sure to set the gamepad or all gamepads will be used!
-                    .set_gamepad(gamepad)
-                    .build();
+            let input_source = InputSource::Gamepad(gamepad);
+            if !joined_players.0.contains_key(&input_source) {
+                info!("Player {} has joined the game!", gamepad.id);
+
+                // Use the bindings saved for this kind of controller, if any
+                let bindings = controls_config.bindings_for(gamepads.name(gamepad));
 
                 let player = spawn_player(
                     &mut commands,
-                    &asset_server,
-                    &mut texture_atlases,
-                    input_map,
-                    gamepad,
-                    &mut meshes,
-                    &mut materials,
+                    bindings.input_map(gamepad),
+                    input_source,
+                    next_player_id(&players),
                 );
+                commands
+                    .entity(player)
+                    .insert(RumbleIntensity(bindings.rumble));
 
-                // Insert the created player and its gamepad to the hashmap of joined players
+                // Insert the created player and its input source to the hashmap of joined players
                 // Since uniqueness was already checked above, we can insert here unchecked
-                joined_players.0.insert_unique_unchecked(gamepad, player);
+                joined_players
+                    .0
+                    .insert_unique_unchecked(input_source, player);
             }
         }
     }
 }
 
-fn disconnect(
+fn join_keyboard(
     mut commands: Commands,
-    action_query: Query<(&ActionState<PlayerAction>, &Player)>,
     mut joined_players: ResMut<JoinedPlayers>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    players: Query<&Player>,
+) {
+    let keyboard_sources = [
+        InputSource::KeyboardMouse,
+        InputSource::Keyboard(KeyboardLayout::Left),
+        InputSource::Keyboard(KeyboardLayout::Right),
+    ];
+
+    for input_source in keyboard_sources {
+        if !keyboard_input.all_pressed(join_keys(input_source).iter().copied())
+            || joined_players.0.contains_key(&input_source)
+        {
+            continue;
+        }
+
+        // Keyboard + mouse and the left shared layout both move with WASD
+        let conflicting_source = match input_source {
+            InputSource::KeyboardMouse => Some(InputSource::Keyboard(KeyboardLayout::Left)),
+            InputSource::Keyboard(KeyboardLayout::Left) => Some(InputSource::KeyboardMouse),
+            _ => None,
+        };
+        if conflicting_source.is_some_and(|source| joined_players.0.contains_key(&source)) {
+            continue;
+        }
+
+        info!("Keyboard player {:?} has joined the game!", input_source);
+
+        let input_map = match input_source {
+            InputSource::Keyboard(layout) => shared_keyboard_input_map(layout),
+            _ => keyboard_mouse_input_map(),
+        };
+
+        let player = spawn_player(
+            &mut commands,
+            input_map,
+            input_source,
+            next_player_id(&players),
+        );
+        match input_source {
+            InputSource::KeyboardMouse => commands.entity(player).insert(MouseAim),
+            _ => commands.entity(player).insert(KeyAim),
+        };
+
+        joined_players
+            .0
+            .insert_unique_unchecked(input_source, player);
+        // Only one keyboard player can join per frame so they don't share a player id
+        return;
+    }
+}
+
+// The keys that have to be held together to join with a keyboard input source
+fn join_keys(input_source: InputSource) -> &'static [KeyCode] {
+    match input_source {
+        InputSource::Keyboard(KeyboardLayout::Left) => &[KeyCode::KeyQ, KeyCode::KeyE],
+        InputSource::Keyboard(KeyboardLayout::Right) => {
+            &[KeyCode::ShiftRight, KeyCode::ControlRight]
+        }
+        _ => &[KeyCode::Enter],
+    }
+}
+
+fn keyboard_mouse_input_map() -> InputMap<PlayerAction> {
+    InputMap::default()
+        .insert_multiple([
+            (PlayerAction::Up, KeyCode::KeyW),
+            (PlayerAction::Down, KeyCode::KeyS),
+            (PlayerAction::Left, KeyCode::KeyA),
+            (PlayerAction::Right, KeyCode::KeyD),
+            (PlayerAction::Dash, KeyCode::ShiftLeft),
+            (PlayerAction::Grab, KeyCode::KeyE),
+            (PlayerAction::CallForPass, KeyCode::KeyQ),
+            (PlayerAction::Start, KeyCode::Space),
+            (PlayerAction::Disconnect, KeyCode::Backspace),
+        ])
+        .insert_multiple([
+            (PlayerAction::Throw, MouseButton::Left),
+            (PlayerAction::Shield, MouseButton::Right),
+        ])
+        .build()
+}
+
+// Two players can share one keyboard, one on each side of it
+fn shared_keyboard_input_map(layout: KeyboardLayout) -> InputMap<PlayerAction> {
+    match layout {
+        KeyboardLayout::Left => InputMap::default()
+            .insert_multiple([
+                (PlayerAction::Up, KeyCode::KeyW),
+                (PlayerAction::Down, KeyCode::KeyS),
+                (PlayerAction::Left, KeyCode::KeyA),
+                (PlayerAction::Right, KeyCode::KeyD),
+                (PlayerAction::Throw, KeyCode::KeyQ),
+                (PlayerAction::Dash, KeyCode::KeyE),
+                (PlayerAction::Shield, KeyCode::KeyR),
+                (PlayerAction::Grab, KeyCode::KeyF),
+                (PlayerAction::CallForPass, KeyCode::KeyC),
+                (PlayerAction::Start, KeyCode::Tab),
+                (PlayerAction::Disconnect, KeyCode::Backquote),
+            ])
+            .build(),
+        KeyboardLayout::Right => InputMap::default()
+            .insert_multiple([
+                (PlayerAction::Up, KeyCode::ArrowUp),
+                (PlayerAction::Down, KeyCode::ArrowDown),
+                (PlayerAction::Left, KeyCode::ArrowLeft),
+                (PlayerAction::Right, KeyCode::ArrowRight),
+                (PlayerAction::Throw, KeyCode::ShiftRight),
+                (PlayerAction::Dash, KeyCode::ControlRight),
+                (PlayerAction::Shield, KeyCode::Period),
+                (PlayerAction::Grab, KeyCode::Comma),
+                (PlayerAction::CallForPass, KeyCode::KeyM),
+                (PlayerAction::Start, KeyCode::Slash),
+                (PlayerAction::Disconnect, KeyCode::Delete),
+            ])
+            .build(),
+    }
+}
+
+// Aims in one of 8 directions from the held movement keys, for keyboard players without a mouse
+fn aim_with_keys(mut players: Query<&mut ActionState<PlayerAction>, With<KeyAim>>) {
+    for mut action_state in players.iter_mut() {
+        let mut direction = Vec2::ZERO;
+        if action_state.pressed(&PlayerAction::Up) {
+            direction.y += 1.0;
+        }
+        if action_state.pressed(&PlayerAction::Down) {
+            direction.y -= 1.0;
+        }
+        if action_state.pressed(&PlayerAction::Left) {
+            direction.x -= 1.0;
+        }
+        if action_state.pressed(&PlayerAction::Right) {
+            direction.x += 1.0;
+        }
+
+        // Keep the last aim direction while no key is held
+        if direction == Vec2::ZERO {
+            continue;
+        }
+
+        action_state.press(&PlayerAction::Aim);
+        action_state
+            .action_data_mut_or_default(&PlayerAction::Aim)
+            .axis_pair = Some(DualAxisData::from_xy(direction.normalize()));
+    }
+}
+
+// Feeds the direction from the player to the cursor into the aim action,
+// so mouse players aim the same way as a right stick would
+fn aim_with_mouse(
+    mut players: Query<(&mut ActionState<PlayerAction>, &GlobalTransform), With<MouseAim>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
 ) {
-    for (action_state, player) in action_query.iter() {
-        if action_state.pressed(&PlayerAction::Disconnect) {
-            let player_entity = *joined_players.0.get(&player.gamepad).unwrap();
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(cursor_position) = window.cursor_position() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(cursor_world_position) =
+        camera.viewport_to_world_2d(camera_transform, cursor_position)
+    else {
+        return;
+    };
+
+    for (mut action_state, player_transform) in players.iter_mut() {
+        let direction =
+            (cursor_world_position - player_transform.translation().truncate()).normalize_or_zero();
+        if direction == Vec2::ZERO {
+            continue;
+        }
+
+        action_state.press(&PlayerAction::Aim);
+        action_state
+            .action_data_mut_or_default(&PlayerAction::Aim)
+            .axis_pair = Some(DualAxisData::from_xy(direction));
+    }
+}
 
+fn disconnect(
+    mut commands: Commands,
+    action_query: Query<(Entity, &ActionState<PlayerAction>, &Player, &Transform)>,
+    mut joined_players: ResMut<JoinedPlayers>,
+    mut possession_changed: EventWriter<PossessionChanged>,
+) {
+    for (player_entity, action_state, player, transform) in action_query.iter() {
+        if action_state.pressed(&PlayerAction::Disconnect)
+            && action_state.current_duration(&PlayerAction::Disconnect) >= DISCONNECT_HOLD
+        {
             // Despawn the disconnected player and remove them from the joined player list
-            commands.entity(player_entity).despawn();
-            joined_players.0.remove(&player.gamepad);
+            despawn_player(
+                &mut commands,
+                player_entity,
+                player,
+                transform.translation.truncate(),
+                &mut joined_players,
+                &mut possession_changed,
+            );
 
-            println!("Player {} has disconnected!", player.gamepad.id);
+            info!("Player {} has disconnected!", player.player_id + 1);
         }
     }
 }
+
+fn handle_gamepad_connections(
+    mut commands: Commands,
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    players: Query<(&Player, &Transform, Has<AwaitingReconnect>)>,
+    mut joined_players: ResMut<JoinedPlayers>,
+    app_state: Res<State<AppState>>,
+    mut countdown: ResMut<CountdownTimer>,
+    mut possession_changed: EventWriter<PossessionChanged>,
+) {
+    for event in connection_events.read() {
+        let input_source = InputSource::Gamepad(event.gamepad);
+        let Some(&player_entity) = joined_players.0.get(&input_source) else {
+            continue;
+        };
+        let Ok((player, transform, awaiting_reconnect)) = players.get(player_entity) else {
+            continue;
+        };
+
+        if event.connected() {
+            if awaiting_reconnect {
+                info!("Player {} has reconnected!", player.player_id + 1);
+                commands.entity(player_entity).remove::<AwaitingReconnect>();
+            }
+            continue;
+        }
+
+        // Outside of a match there is nothing to wait for
+        if app_state.get() != &AppState::InGame {
+            despawn_player(
+                &mut commands,
+                player_entity,
+                player,
+                transform.translation.truncate(),
+                &mut joined_players,
+                &mut possession_changed,
+            );
+            info!("Player {} has disconnected!", player.player_id + 1);
+            continue;
+        }
+
+        info!(
+            "Player {} lost their controller, pausing",
+            player.player_id + 1
+        );
+        commands.entity(player_entity).insert(AwaitingReconnect);
+        commands.insert_resource(NextState::Pending(GameState::Paused));
+        // Stop a countdown that is already running from resuming the match
+        countdown.stop();
+    }
+}
+
+// Any player still connected can press Start to go on without the missing players
+pub fn leave_disconnected_players(
+    mut commands: Commands,
+    players: Query<(
+        Entity,
+        &ActionState<PlayerAction>,
+        &Player,
+        &Transform,
+        Has<AwaitingReconnect>,
+    )>,
+    mut joined_players: ResMut<JoinedPlayers>,
+    mut possession_changed: EventWriter<PossessionChanged>,
+) {
+    let continue_pressed = players
+        .iter()
+        .any(|(_, action_state, _, _, awaiting_reconnect)| {
+            !awaiting_reconnect && action_state.just_pressed(&PlayerAction::Start)
+        });
+    if !continue_pressed {
+        return;
+    }
+
+    for (player_entity, _, player, transform, awaiting_reconnect) in players.iter() {
+        if !awaiting_reconnect {
+            continue;
+        }
+
+        info!("Player {} has left the match", player.player_id + 1);
+        despawn_player(
+            &mut commands,
+            player_entity,
+            player,
+            transform.translation.truncate(),
+            &mut joined_players,
+            &mut possession_changed,
+        );
+    }
+}
+
+// Restart the countdown once nobody is missing anymore
+fn resume_after_reconnect(
+    mut reconnected: RemovedComponents<AwaitingReconnect>,
+    awaiting_reconnect: Query<(), With<AwaitingReconnect>>,
+    mut countdown: ResMut<CountdownTimer>,
+) {
+    if reconnected.read().count() == 0 || !awaiting_reconnect.is_empty() {
+        return;
+    }
+
+    countdown.start();
+}
@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use rand::Rng;
+
+use crate::{
+    ball::{spawn_loose_ball, PossessionCause, PossessionChanged},
+    direction_indicator::DirectionIndicator,
+    gamepad::PlayerAction,
+    player::Player,
+    revive::Downed,
+    rng::GameRng,
+    simulation::GameplaySet,
+    sprites::{AnimationIndices, AnimationTimer},
+    tuning::Tuning,
+};
+
+pub struct GrabPlugin;
+
+impl Plugin for GrabPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (grab_ball, credit_steals)
+                .chain()
+                .in_set(GameplaySet::Steal),
+        );
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Grab {
+    winding_up: bool,
+    wind_up: Timer,
+    cooldown: Timer,
+}
+
+impl Grab {
+    pub fn is_winding_up(&self) -> bool {
+        self.winding_up
+    }
+}
+
+impl Default for Grab {
+    fn default() -> Self {
+        Self {
+            winding_up: false,
+            wind_up: Timer::from_seconds(0.0, TimerMode::Once),
+            cooldown: Timer::from_seconds(0.0, TimerMode::Once),
+        }
+    }
+}
+
+// Marks the child sprite that is shown during the grab wind-up
+#[derive(Component)]
+pub struct GrabSprite;
+
+pub fn spawn_grab_sprite(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    texture_atlases_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let layout = TextureAtlasLayout::from_grid(UVec2::new(64, 64), 2, 1, None, None);
+    let animation_indices = AnimationIndices { first: 0, last: 1 };
+
+    parent.spawn((
+        SpriteSheetBundle {
+            texture: asset_server.load("Ducky/Spritesheets/grab.png"),
+            atlas: TextureAtlas {
+                layout: texture_atlases_layouts.add(layout),
+                index: animation_indices.first,
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        animation_indices,
+        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+        GrabSprite,
+    ));
+}
+
+fn grab_ball(
+    mut commands: Commands,
+    mut grabbers: Query<
+        (Entity, &ActionState<PlayerAction>, &Transform, &mut Grab),
+        Without<Downed>,
+    >,
+    mut players: Query<(Entity, &Transform, &mut Player)>,
+    indicators: Query<(Entity, &Parent), With<DirectionIndicator>>,
+    mut possession_changed: EventWriter<PossessionChanged>,
+    mut rng: ResMut<GameRng>,
+    tuning: Res<Tuning>,
+    time: Res<Time>,
+) {
+    let grab_tuning = tuning.grab;
+
+    for (grabber, action_state, grabber_transform, mut grab) in grabbers.iter_mut() {
+        grab.cooldown.tick(time.delta());
+
+        if !grab.winding_up {
+            if action_state.just_pressed(&PlayerAction::Grab) && grab.cooldown.finished() {
+                grab.winding_up = true;
+                grab.wind_up = Timer::from_seconds(grab_tuning.wind_up, TimerMode::Once);
+            }
+            continue;
+        }
+
+        grab.wind_up.tick(time.delta());
+        if !grab.wind_up.finished() {
+            continue;
+        }
+
+        grab.winding_up = false;
+        grab.cooldown = Timer::from_seconds(grab_tuning.cooldown, TimerMode::Once);
+
+        // Only a carrier within reach at the end of the wind-up can be robbed
+        let carrier = players
+            .iter()
+            .find(|(entity, transform, player)| {
+                *entity != grabber
+                    && player.have_ball
+                    && transform
+                        .translation
+                        .distance(grabber_transform.translation)
+                        <= grab_tuning.range
+            })
+            .map(|(entity, transform, _)| (entity, transform.translation.truncate()));
+
+        let Some((carrier, carrier_position)) = carrier else {
+            continue;
+        };
+
+        if let Ok((_, _, mut carrier_player)) = players.get_mut(carrier) {
+            carrier_player.have_ball = false;
+        }
+        let indicator = indicators
+            .iter()
+            .find(|(_, parent)| parent.get() == carrier)
+            .map(|(entity, _)| entity);
+
+        if rng.gameplay.gen_bool(grab_tuning.fumble_chance) {
+            info!("Grab by {:?} fumbled the ball loose", grabber);
+            if let Some(indicator) = indicator {
+                commands.entity(indicator).despawn_recursive();
+            }
+            spawn_loose_ball(&mut commands, carrier_position);
+            possession_changed.send(PossessionChanged {
+                from: Some(carrier),
+                to: None,
+                cause: PossessionCause::Fumble,
+            });
+            continue;
+        }
+
+        info!("Player {:?} stole the ball from {:?}", grabber, carrier);
+        if let Ok((_, _, mut grabber_player)) = players.get_mut(grabber) {
+            grabber_player.have_ball = true;
+        }
+        if let Some(indicator) = indicator {
+            commands.entity(grabber).add_child(indicator);
+        }
+        possession_changed.send(PossessionChanged {
+            from: Some(carrier),
+            to: Some(grabber),
+            cause: PossessionCause::Steal,
+        });
+    }
+}
+
+fn credit_steals(
+    mut possession_changed: EventReader<PossessionChanged>,
+    mut players: Query<&mut Player>,
+    tuning: Res<Tuning>,
+) {
+    for event in possession_changed.read() {
+        let (PossessionCause::Steal, Some(thief)) = (event.cause, event.to) else {
+            continue;
+        };
+        let Ok(mut player) = players.get_mut(thief) else {
+            continue;
+        };
+
+        player.score += tuning.grab.steal_bonus;
+    }
+}
+
+pub fn show_grab(
+    players: Query<&Grab>,
+    mut grab_sprites: Query<(&Parent, &mut Visibility), With<GrabSprite>>,
+) {
+    for (parent, mut visibility) in grab_sprites.iter_mut() {
+        let Ok(grab) = players.get(parent.get()) else {
+            continue;
+        };
+
+        *visibility = if grab.is_winding_up() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, ToSocketAddrs},
+};
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_rapier2d::prelude::Velocity as RigidBodyVelocity;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    ball::Ball,
+    bot::{add_bots, BotSkill},
+    enemy::Enemy,
+    gamepad::PlayerAction,
+    headless,
+    input_buffer::{buffer_just_pressed, FixedInputSet, FixedTick, InputBuffer},
+    player::{spawn_player, Player, Velocity},
+    resources::{InputSource, JoinedPlayers, MatchSeed},
+    revive::Downed,
+    shield::Shield,
+    tuning::{Tuning, TuningOverride},
+    AppState, BirdstompSettings, GameState,
+};
+
+// Reward lost for every life an agent loses, in the same unit as the score
+const LIFE_PENALTY: f32 = 10.0;
+// Longest the countdown at the start of a match can take
+const COUNTDOWN_TICK_LIMIT: u32 = 64 * 10;
+
+#[derive(Error, Debug)]
+pub enum GymError {
+    #[error("step was called before reset")]
+    NotReset,
+    #[error("expected {expected} agent actions, got {received}")]
+    WrongActionCount { expected: usize, received: usize },
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("connection failed: {0}")]
+    Io(#[from] io::Error),
+}
+
+// How to set up the match on reset
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ResetOptions {
+    pub seed: u64,
+    // Players controlled through `step`
+    pub agents: usize,
+    // Computer controlled players that join after the agents
+    pub bots: Vec<BotSkill>,
+    // Fixed ticks simulated by every step
+    pub ticks_per_step: u32,
+    // The match counts as done after this many ticks even without a game over
+    pub max_ticks: u64,
+    // Played with instead of the default tuning file
+    pub tuning: Option<Tuning>,
+}
+
+impl Default for ResetOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            agents: 1,
+            bots: Vec::new(),
+            ticks_per_step: 1,
+            max_ticks: 64 * 60 * 5,
+            tuning: None,
+        }
+    }
+}
+
+// The input of one agent for a step. Buttons stay held until a step releases
+// them, so pressing again needs a step with the button released in between
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct AgentAction {
+    pub movement: Vec2,
+    pub aim: Vec2,
+    pub throw: bool,
+    pub dash: bool,
+    pub shield: bool,
+    pub grab: bool,
+    pub call_for_pass: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Observation {
+    pub tick: u64,
+    // False while the countdown after a hit is running, actions do nothing then
+    pub running: bool,
+    pub players: Vec<PlayerObservation>,
+    pub ball: BallObservation,
+    pub enemy: Option<EnemyObservation>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PlayerObservation {
+    pub player_id: usize,
+    // Index into the actions passed to `step`, `None` for bots
+    pub agent: Option<usize>,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub lives: u32,
+    pub score: u32,
+    pub has_ball: bool,
+    pub shield_raised: bool,
+    pub downed: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BallObservation {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    // Player id of the carrier, `None` while the ball is loose
+    pub holder: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EnemyObservation {
+    pub position: Vec2,
+    pub speed: f32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StepResult {
+    pub observation: Observation,
+    // One reward per agent: score gained minus a penalty for every life lost
+    pub reward: Vec<f32>,
+    pub done: bool,
+}
+
+// A training environment around the headless simulation. Every reset starts
+// a fresh app, so matches never leak into each other
+#[derive(Default)]
+pub struct GymEnv {
+    current: Option<GymMatch>,
+}
+
+struct GymMatch {
+    app: App,
+    agents: Vec<Entity>,
+    options: ResetOptions,
+    ticks: u64,
+    // Score and lives of every agent after the last step
+    last: Vec<(u32, u32)>,
+}
+
+// Agent input for the next tick, written right before the gameplay systems read it
+#[derive(Resource, Default)]
+struct PendingAgentActions(Vec<(Entity, AgentAction)>);
+
+impl GymEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self, options: ResetOptions) -> Observation {
+        let mut app = headless::app(BirdstompSettings {
+            replays: false,
+            ..BirdstompSettings::headless()
+        });
+        app.insert_resource(MatchSeed(options.seed))
+            .init_resource::<PendingAgentActions>()
+            .add_systems(FixedPreUpdate, apply_agent_actions.in_set(FixedInputSet));
+        if let Some(tuning) = options.tuning.clone() {
+            app.insert_resource(TuningOverride(tuning));
+        }
+        app.finish();
+        app.cleanup();
+        app.update();
+
+        let agents: Vec<Entity> = (0..options.agents)
+            .map(|agent| {
+                app.world_mut().run_system_once(
+                    move |mut commands: Commands, mut joined_players: ResMut<JoinedPlayers>| {
+                        let input_source = InputSource::Agent(agent);
+                        let player =
+                            spawn_player(&mut commands, InputMap::default(), input_source, agent);
+                        joined_players.0.insert(input_source, player);
+                        player
+                    },
+                )
+            })
+            .collect();
+        add_bots(app.world_mut(), &options.bots);
+
+        app.world_mut()
+            .insert_resource(NextState::Pending(AppState::InGame));
+        for _ in 0..COUNTDOWN_TICK_LIMIT {
+            app.update();
+            if app.world().resource::<State<GameState>>().get() == &GameState::Running {
+                break;
+            }
+        }
+
+        let mut current = GymMatch {
+            app,
+            agents,
+            options,
+            ticks: 0,
+            last: Vec::new(),
+        };
+        current.last = current.agent_progress();
+        let observation = current.observe();
+        self.current = Some(current);
+        observation
+    }
+
+    // The world of the current match, to read anything the observation leaves out
+    pub fn world(&self) -> Option<&World> {
+        self.current.as_ref().map(|current| current.app.world())
+    }
+
+    pub fn step(&mut self, actions: &[AgentAction]) -> Result<StepResult, GymError> {
+        let current = self.current.as_mut().ok_or(GymError::NotReset)?;
+        if actions.len() != current.agents.len() {
+            return Err(GymError::WrongActionCount {
+                expected: current.agents.len(),
+                received: actions.len(),
+            });
+        }
+
+        current
+            .app
+            .world_mut()
+            .resource_mut::<PendingAgentActions>()
+            .0 = current
+            .agents
+            .iter()
+            .copied()
+            .zip(actions.iter().copied())
+            .collect();
+
+        let mut game_over = false;
+        for _ in 0..current.options.ticks_per_step {
+            current.app.update();
+            current.ticks += 1;
+            game_over =
+                current.app.world().resource::<State<AppState>>().get() == &AppState::GameOver;
+            if game_over {
+                break;
+            }
+        }
+
+        let progress = current.agent_progress();
+        let reward = progress
+            .iter()
+            .zip(&current.last)
+            .map(|((score, lives), (last_score, last_lives))| {
+                score.saturating_sub(*last_score) as f32
+                    - last_lives.saturating_sub(*lives) as f32 * LIFE_PENALTY
+            })
+            .collect();
+        current.last = progress;
+
+        Ok(StepResult {
+            observation: current.observe(),
+            reward,
+            done: game_over || current.ticks >= current.options.max_ticks,
+        })
+    }
+}
+
+impl GymMatch {
+    fn agent_progress(&self) -> Vec<(u32, u32)> {
+        self.agents
+            .iter()
+            .map(|agent| {
+                self.app
+                    .world()
+                    .get::<Player>(*agent)
+                    .map_or((0, 0), |player| (player.score, player.lives))
+            })
+            .collect()
+    }
+
+    fn observe(&mut self) -> Observation {
+        let world = self.app.world_mut();
+        let tick = world.resource::<FixedTick>().0;
+        let running = world.resource::<State<GameState>>().get() == &GameState::Running;
+
+        let mut players: Vec<PlayerObservation> = world
+            .query::<(Entity, &Player, &Transform, &Velocity, &Shield, Has<Downed>)>()
+            .iter(world)
+            .map(
+                |(entity, player, transform, velocity, shield, downed)| PlayerObservation {
+                    player_id: player.player_id,
+                    agent: self.agents.iter().position(|agent| *agent == entity),
+                    position: transform.translation.truncate(),
+                    velocity: velocity.0,
+                    lives: player.lives,
+                    score: player.score,
+                    has_ball: player.have_ball,
+                    shield_raised: shield.is_raised(),
+                    downed,
+                },
+            )
+            .collect();
+        players.sort_by_key(|player| player.player_id);
+
+        let loose_ball = world
+            .query_filtered::<(&Transform, Option<&RigidBodyVelocity>), With<Ball>>()
+            .get_single(world)
+            .ok()
+            .map(|(transform, velocity)| BallObservation {
+                position: transform.translation.truncate(),
+                velocity: velocity.map_or(Vec2::ZERO, |velocity| velocity.linvel),
+                holder: None,
+            });
+        let ball = loose_ball.unwrap_or_else(|| {
+            let holder = players.iter().find(|player| player.has_ball);
+            BallObservation {
+                position: holder.map_or(Vec2::ZERO, |player| player.position),
+                velocity: holder.map_or(Vec2::ZERO, |player| player.velocity),
+                holder: holder.map(|player| player.player_id),
+            }
+        });
+
+        let enemy = world
+            .query::<(&Transform, &Enemy)>()
+            .get_single(world)
+            .ok()
+            .map(|(transform, enemy)| EnemyObservation {
+                position: transform.translation.truncate(),
+                speed: enemy.speed,
+            });
+
+        Observation {
+            tick,
+            running,
+            players,
+            ball,
+            enemy,
+        }
+    }
+}
+
+fn apply_agent_actions(
+    mut pending: ResMut<PendingAgentActions>,
+    mut players: Query<(&mut ActionState<PlayerAction>, &mut InputBuffer)>,
+    tick: Res<FixedTick>,
+) {
+    for (entity, action) in pending.0.drain(..) {
+        let Ok((mut action_state, mut input_buffer)) = players.get_mut(entity) else {
+            continue;
+        };
+
+        for (player_action, direction) in [
+            (PlayerAction::Move, action.movement),
+            (PlayerAction::Aim, action.aim),
+        ] {
+            if direction == Vec2::ZERO {
+                action_state.release(&player_action);
+                continue;
+            }
+            action_state.press(&player_action);
+            action_state
+                .action_data_mut_or_default(&player_action)
+                .axis_pair = Some(DualAxisData::from_xy(direction.clamp_length_max(1.0)));
+        }
+
+        for (player_action, held) in [
+            (PlayerAction::Throw, action.throw),
+            (PlayerAction::Dash, action.dash),
+            (PlayerAction::Shield, action.shield),
+            (PlayerAction::Grab, action.grab),
+            (PlayerAction::CallForPass, action.call_for_pass),
+        ] {
+            if held {
+                action_state.press(&player_action);
+            } else {
+                action_state.release(&player_action);
+            }
+        }
+
+        buffer_just_pressed(&action_state, &mut input_buffer, &tick);
+    }
+}
+
+// One JSON request per line, answered with one JSON response per line:
+//   {"reset": {"seed": 7, "agents": 1, "bots": ["Hard"]}}
+//   {"step": {"actions": [{"movement": [1.0, 0.0], "throw": true}]}}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum GymRequest {
+    Reset(ResetOptions),
+    Step { actions: Vec<AgentAction> },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum GymResponse {
+    Reset(Observation),
+    Step(StepResult),
+    Error(String),
+}
+
+// Serves a `GymEnv` on a local socket, one client at a time. The game logs to
+// stdout, so the protocol gets a connection of its own
+pub fn serve_tcp(address: impl ToSocketAddrs) -> Result<(), GymError> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!("Gym listening on {}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = BufReader::new(stream.try_clone()?);
+        if let Err(error) = serve(reader, stream) {
+            eprintln!("Gym client disconnected: {error}");
+        }
+    }
+    Ok(())
+}
+
+// Answers requests until the reader runs out, every client gets a fresh environment
+pub fn serve(reader: impl BufRead, mut writer: impl Write) -> Result<(), GymError> {
+    let mut env = GymEnv::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<GymRequest>(&line) {
+            Ok(GymRequest::Reset(options)) => GymResponse::Reset(env.reset(options)),
+            Ok(GymRequest::Step { actions }) => match env.step(&actions) {
+                Ok(result) => GymResponse::Step(result),
+                Err(error) => GymResponse::Error(error.to_string()),
+            },
+            Err(error) => GymResponse::Error(GymError::from(error).to_string()),
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writeln!(writer)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit, asset::AssetPlugin, input::InputPlugin, prelude::*, scene::ScenePlugin,
+    state::app::StatesPlugin, time::TimeUpdateStrategy,
+};
+use serde::Serialize;
+
+use crate::{
+    bot::{add_bots, parse_bot_skills},
+    replay::ReplayPlayback,
+    resources::MatchSeed,
+    simulation::FIXED_TIMESTEP,
+    stats::MatchStats,
+    AppState, BirdstompPlugins, BirdstompSettings,
+};
+
+// Stop after this many ticks even if the match never ends, about 10 minutes of play
+const DEFAULT_TICK_LIMIT: u64 = 64 * 60 * 10;
+
+// What a headless run prints to stdout once it is over
+#[derive(Serialize, Debug)]
+struct RunSummary {
+    seed: u64,
+    ticks: u64,
+    game_over: bool,
+    stats: MatchStats,
+}
+
+// Runs the simulation without a window or renderer, as fast as the machine
+// allows. The players are the ones recorded in `--replay <file>`, or bots
+// added with `--bots easy,hard`. A JSON summary of the match is printed once
+// it ends or `--ticks <n>` ticks have passed, and it is the only thing
+// written to stdout
+pub fn run() -> AppExit {
+    // There is no log output without `LogPlugin`, so errors go straight to stderr
+    let bots = match bots_from_args()
+        .map(|bots| parse_bot_skills(&bots))
+        .transpose()
+    {
+        Ok(bots) => bots,
+        Err(error) => {
+            eprintln!("{error}");
+            return AppExit::error();
+        }
+    };
+
+    // A bot match is a new match, there is nothing to play back
+    let mut app = app(BirdstompSettings {
+        replays: bots.is_none(),
+        ..BirdstompSettings::headless()
+    });
+    let replaying = app.world().contains_resource::<ReplayPlayback>();
+    if !replaying && bots.is_none() {
+        eprintln!(
+            "Headless runs need players, pass a recorded match with --replay <file> or bots with --bots easy,hard"
+        );
+        return AppExit::error();
+    }
+
+    app.finish();
+    app.cleanup();
+    if let Some(bots) = bots {
+        // Runs the startup schedules, then starts the match the way the lobby would
+        app.update();
+        add_bots(app.world_mut(), &bots);
+        app.world_mut()
+            .insert_resource(NextState::Pending(AppState::InGame));
+    }
+
+    let tick_limit = tick_limit_from_args().unwrap_or(DEFAULT_TICK_LIMIT);
+    let mut ticks = 0;
+    let mut game_over = false;
+    while ticks < tick_limit {
+        app.update();
+        ticks += 1;
+
+        let world = app.world();
+        if world.resource::<State<AppState>>().get() == &AppState::GameOver {
+            game_over = true;
+            break;
+        }
+        if replaying && world.resource::<ReplayPlayback>().is_finished() {
+            break;
+        }
+    }
+
+    let world = app.world();
+    let summary = RunSummary {
+        seed: world.resource::<MatchSeed>().0,
+        ticks,
+        game_over,
+        stats: world.resource::<MatchStats>().clone(),
+    };
+    match serde_json::to_string_pretty(&summary) {
+        Ok(json) => {
+            println!("{json}");
+            AppExit::Success
+        }
+        Err(error) => {
+            eprintln!("Could not write the run summary: {error}");
+            AppExit::error()
+        }
+    }
+}
+
+// The game on top of the few Bevy plugins the simulation needs. Every
+// `update` advances the clock by exactly one simulation tick
+pub fn app(settings: BirdstompSettings) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        ScenePlugin,
+        StatesPlugin,
+        InputPlugin,
+    ))
+    // Rapier registers its async colliders against meshes, even when none are used
+    .init_asset::<Mesh>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        FIXED_TIMESTEP,
+    )))
+    .add_plugins(BirdstompPlugins::new(settings));
+    app
+}
+
+fn bots_from_args() -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--bots");
+    args.next()?;
+    args.next()
+}
+
+fn tick_limit_from_args() -> Option<u64> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--ticks");
+    args.next()?;
+    args.next()?.parse().ok()
+}
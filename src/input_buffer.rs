@@ -0,0 +1,88 @@
+use bevy::{prelude::*, utils::HashMap};
+use leafwing_input_manager::prelude::*;
+
+use crate::gamepad::PlayerAction;
+
+// Actions whose presses are remembered for a few ticks instead of only the frame they happen in
+const BUFFERED_ACTIONS: [PlayerAction; 2] = [PlayerAction::Throw, PlayerAction::Dash];
+
+pub struct InputBufferPlugin;
+
+impl Plugin for InputBufferPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FixedTick>()
+            .add_systems(FixedFirst, advance_fixed_tick)
+            .add_systems(
+                PreUpdate,
+                buffer_presses.after(InputManagerSystem::ManualControl),
+            );
+    }
+}
+
+// Systems that write action states in FixedPreUpdate instead of devices, like
+// bots and scripts. Anything reading the input of the tick runs after them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FixedInputSet;
+
+// Number of fixed timesteps run so far, used to timestamp buffered presses
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct FixedTick(pub u64);
+
+#[derive(Component, Default, Debug)]
+pub struct InputBuffer {
+    presses: HashMap<PlayerAction, u64>,
+}
+
+impl InputBuffer {
+    pub fn press(&mut self, action: PlayerAction, tick: u64) {
+        self.presses.insert(action, tick);
+    }
+
+    // Takes a press of `action` made at most `window` ticks before `now`.
+    // The press is used up, so it only triggers one gameplay action
+    pub fn consume(&mut self, action: PlayerAction, now: u64, window: u64) -> bool {
+        match self.presses.get(&action) {
+            Some(&tick) if now.saturating_sub(tick) <= window => {
+                self.presses.remove(&action);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn advance_fixed_tick(mut tick: ResMut<FixedTick>) {
+    tick.0 += 1;
+}
+
+fn buffer_presses(
+    mut players: Query<(&ActionState<PlayerAction>, &mut InputBuffer)>,
+    tick: Res<FixedTick>,
+) {
+    for (action_state, mut input_buffer) in players.iter_mut() {
+        stamp_presses(action_state, &mut input_buffer, tick.0);
+    }
+}
+
+// For action states written in FixedPreUpdate by bots, replays and scripts instead
+// of devices. `FixedTick` has already advanced for the tick by then, the presses get
+// the tick `buffer_presses` would have given them so they play out the same
+pub fn buffer_just_pressed(
+    action_state: &ActionState<PlayerAction>,
+    input_buffer: &mut InputBuffer,
+    tick: &FixedTick,
+) {
+    stamp_presses(action_state, input_buffer, tick.0.saturating_sub(1));
+}
+
+fn stamp_presses(
+    action_state: &ActionState<PlayerAction>,
+    input_buffer: &mut InputBuffer,
+    tick: u64,
+) {
+    for action in BUFFERED_ACTIONS {
+        if action_state.just_pressed(&action) {
+            input_buffer.press(action, tick);
+        }
+    }
+}
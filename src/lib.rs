@@ -0,0 +1,198 @@
+pub mod asset_loader;
+pub mod balance;
+pub mod ball;
+pub mod bot;
+pub mod call_for_pass;
+pub mod controls;
+pub mod direction_indicator;
+pub mod enemy;
+pub mod feedback;
+pub mod gamepad;
+pub mod grab;
+pub mod gym;
+pub mod headless;
+pub mod input_buffer;
+pub mod lobby;
+pub mod match_setup;
+pub mod navigation;
+pub mod pause;
+pub mod player;
+pub mod presentation;
+pub mod replay;
+pub mod resources;
+pub mod revive;
+pub mod rng;
+pub mod rounds;
+pub mod shield;
+pub mod simulation;
+pub mod sprites;
+pub mod stats;
+pub mod tuning;
+pub mod ui;
+
+use asset_loader::AssetLoaderPlugin;
+use bevy::{app::PluginGroupBuilder, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+use crate::ball::BallPlugin;
+use crate::bot::BotPlugin;
+use crate::call_for_pass::CallForPassPlugin;
+use crate::controls::ControlsPlugin;
+use crate::direction_indicator::DirectionIndicatorPlugin;
+use crate::enemy::EnemyPlugin;
+use crate::feedback::FeedbackPlugin;
+use crate::gamepad::GamepadPlugin;
+use crate::grab::GrabPlugin;
+use crate::input_buffer::InputBufferPlugin;
+use crate::lobby::LobbyPlugin;
+use crate::match_setup::MatchPlugin;
+use crate::pause::PausePlugin;
+use crate::player::PlayerPlugin;
+use crate::presentation::PresentationPlugin;
+use crate::replay::ReplayPlugin;
+use crate::revive::RevivePlugin;
+use crate::rng::RngPlugin;
+use crate::rounds::RoundsPlugin;
+use crate::shield::ShieldPlugin;
+use crate::simulation::SimulationPlugin;
+use crate::sprites::SpritePlugin;
+use crate::stats::StatsPlugin;
+use crate::tuning::TuningPlugin;
+use crate::ui::UiPlugin;
+
+// x coordinates
+const LEFT_WALL: f32 = -640.;
+const RIGHT_WALL: f32 = 640.;
+// y coordinates
+const BOTTOM_WALL: f32 = -460.;
+const TOP_WALL: f32 = 512.;
+
+const FLOOR_THICKNESS: f32 = 5.0;
+const COLOR_FLOOR: Color = Color::rgb(0.45, 0.55, 0.66);
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, States)]
+pub enum AppState {
+    #[default]
+    Menu,
+    InGame,
+    GameOver,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, States)]
+pub enum GameState {
+    #[default]
+    Paused,
+    Running,
+}
+
+// How the game is put together by `BirdstompPlugins`
+#[derive(Debug, Clone)]
+pub struct BirdstompSettings {
+    pub window_title: String,
+    pub window_resolution: Vec2,
+    // Draw the Rapier colliders on top of the game
+    pub debug_render: bool,
+    // Sprites and UI. Without it only the simulation runs, and the
+    // app can be driven from `MinimalPlugins`
+    pub presentation: bool,
+    // Record every match, or play one back with `--replay <file>`
+    pub replays: bool,
+    // Seed every match is played from, a random one when unset. Defaults to
+    // `BIRDSTOMP_SEED`, and `--seed <number>` overrides it
+    pub seed: Option<u64>,
+}
+
+impl Default for BirdstompSettings {
+    fn default() -> Self {
+        Self {
+            window_title: "Stomp".into(),
+            window_resolution: Vec2::new(1280., 1024.),
+            debug_render: true,
+            presentation: true,
+            replays: true,
+            seed: rng::seed_from_env(),
+        }
+    }
+}
+
+impl BirdstompSettings {
+    pub fn headless() -> Self {
+        Self {
+            debug_render: false,
+            presentation: false,
+            ..default()
+        }
+    }
+
+    // Bevy's `DefaultPlugins` with the window these settings describe
+    pub fn default_plugins(&self) -> PluginGroupBuilder {
+        DefaultPlugins
+            .set(ImagePlugin::default_nearest())
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: self.window_title.clone(),
+                    resolution: self.window_resolution.into(),
+                    ..default()
+                }),
+                ..default()
+            })
+    }
+}
+
+// Everything the game adds on top of Bevy's own plugins, in the order it needs
+#[derive(Default)]
+pub struct BirdstompPlugins {
+    pub settings: BirdstompSettings,
+}
+
+impl BirdstompPlugins {
+    pub fn new(settings: BirdstompSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl PluginGroup for BirdstompPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let mut group = PluginGroupBuilder::start::<Self>()
+            .add(MatchPlugin)
+            // Physics steps together with the gameplay systems, see `SimulationPlugin`
+            .add(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule())
+            .add(SimulationPlugin)
+            .add(TuningPlugin)
+            .add(ControlsPlugin)
+            .add(GamepadPlugin)
+            .add(InputBufferPlugin)
+            .add(RngPlugin {
+                seed: self.settings.seed,
+            })
+            .add(PlayerPlugin)
+            .add(ShieldPlugin)
+            .add(GrabPlugin)
+            .add(CallForPassPlugin)
+            .add(RevivePlugin)
+            .add(RoundsPlugin)
+            .add(LobbyPlugin)
+            .add(PausePlugin)
+            .add(EnemyPlugin)
+            .add(BallPlugin)
+            .add(DirectionIndicatorPlugin)
+            .add(StatsPlugin)
+            .add(FeedbackPlugin)
+            .add(BotPlugin);
+
+        if self.settings.replays {
+            group = group.add(ReplayPlugin);
+        }
+        if self.settings.debug_render {
+            group = group.add(RapierDebugRenderPlugin::default());
+        }
+        if self.settings.presentation {
+            group = group
+                .add(AssetLoaderPlugin)
+                .add(UiPlugin)
+                .add(SpritePlugin)
+                .add(PresentationPlugin);
+        }
+        group
+    }
+}
@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::gamepad::PlayerAction;
+use crate::navigation::{read_menu_input, step_selection, MenuInput};
+use crate::player::{Appearance, Character, Player, PLAYER_COLORS};
+use crate::resources::InputSource;
+use crate::AppState;
+
+// How long everyone has to stay ready before the match starts
+pub const LOBBY_COUNTDOWN_SECONDS: f32 = 3.0;
+
+// Joined players pick how they look, change the match settings and ready up
+// while the menu is open. The match starts once everyone has stayed ready for
+// the countdown, `UiPlugin` only draws the lobby
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChangeLobbySetting>()
+            .init_resource::<LobbySettings>()
+            .configure_sets(Update, LobbySet.run_if(in_state(AppState::Menu)))
+            .add_systems(
+                Update,
+                (seat_new_players, lobby_input, count_down_to_match)
+                    .chain()
+                    .in_set(LobbySet),
+            )
+            .add_systems(OnExit(AppState::Menu), leave_lobby);
+    }
+}
+
+// The lobby systems, anything that draws the lobby runs after them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LobbySet;
+
+// A joined player's place in the lobby, kept on the player for as long as the lobby is open
+#[derive(Component, Debug)]
+pub struct LobbySlot {
+    pub ready: bool,
+    // See `read_menu_input`
+    stick_tilted: bool,
+    // The join buttons can include the ready button, nothing counts until they are let go
+    waiting_for_release: bool,
+}
+
+// Runs while everyone is ready, the match starts once it is over
+#[derive(Resource, Debug)]
+pub struct LobbyCountdown(pub Timer);
+
+// The match settings that can be changed from the lobby, in the order they are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LobbySetting {
+    Bots,
+    BotSkill,
+    RoundsToWin,
+    RoundTime,
+    Mode,
+}
+
+impl LobbySetting {
+    pub const ALL: [LobbySetting; 5] = [
+        LobbySetting::Bots,
+        LobbySetting::BotSkill,
+        LobbySetting::RoundsToWin,
+        LobbySetting::RoundTime,
+        LobbySetting::Mode,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LobbySetting::Bots => "Bots",
+            LobbySetting::BotSkill => "Bot skill",
+            LobbySetting::RoundsToWin => "Rounds to win",
+            LobbySetting::RoundTime => "Round time",
+            LobbySetting::Mode => "Mode",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingStep {
+    Previous,
+    Next,
+}
+
+// Sent when someone steps through a setting in the lobby. The plugin the setting
+// belongs to applies it, the lobby doesn't know what the settings do
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ChangeLobbySetting {
+    pub setting: LobbySetting,
+    pub step: SettingStep,
+}
+
+// One player at a time changes the settings, whoever pressed Grab last
+#[derive(Resource, Debug, Default)]
+pub struct LobbySettings {
+    pub editor: Option<Entity>,
+    // Index into `LobbySetting::ALL`
+    pub selected: usize,
+}
+
+// Gives every player without a slot one. Players keep the color they had
+// before, unless someone else has it by now
+fn seat_new_players(
+    mut commands: Commands,
+    mut new_players: Query<(Entity, &Player, &mut Appearance), Without<LobbySlot>>,
+    seated_players: Query<&Appearance, With<LobbySlot>>,
+) {
+    let mut taken_colors: Vec<usize> = seated_players
+        .iter()
+        .map(|appearance| appearance.color)
+        .collect();
+
+    for (entity, player, mut appearance) in new_players.iter_mut() {
+        if taken_colors.contains(&appearance.color) {
+            appearance.color = next_free_color(appearance.color, MenuInput::Right, &taken_colors);
+        }
+        taken_colors.push(appearance.color);
+
+        // Bots go along with whatever the players decide
+        let bot = matches!(player.input_source, InputSource::Bot(_));
+        commands.entity(entity).insert(LobbySlot {
+            ready: bot,
+            stick_tilted: true,
+            waiting_for_release: true,
+        });
+    }
+}
+
+// Left/Right picks a color, Up/Down a character and Dash or Start toggles ready.
+// Grab switches to the match settings and back, where Up/Down picks a setting and
+// Left/Right changes it. Being ready locks everything in
+fn lobby_input(
+    mut players: Query<(
+        Entity,
+        &Player,
+        &ActionState<PlayerAction>,
+        &mut Appearance,
+        &mut LobbySlot,
+    )>,
+    mut settings: ResMut<LobbySettings>,
+    mut setting_changes: EventWriter<ChangeLobbySetting>,
+) {
+    // Whoever was changing the settings left
+    if settings
+        .editor
+        .is_some_and(|editor| !players.contains(editor))
+    {
+        settings.editor = None;
+    }
+
+    let mut colors: Vec<(Entity, usize)> = players
+        .iter()
+        .map(|(entity, _, _, appearance, _)| (entity, appearance.color))
+        .collect();
+
+    for (entity, player, action_state, mut appearance, mut slot) in players.iter_mut() {
+        if slot.waiting_for_release {
+            if !action_state.pressed(&PlayerAction::Dash)
+                && !action_state.pressed(&PlayerAction::Start)
+            {
+                slot.waiting_for_release = false;
+            }
+            continue;
+        }
+
+        // Only written when it changes, any change redraws the box
+        let mut stick_tilted = slot.stick_tilted;
+        let input = read_menu_input(action_state, &mut stick_tilted);
+        if slot.stick_tilted != stick_tilted {
+            slot.stick_tilted = stick_tilted;
+        }
+
+        let editing = settings.editor == Some(entity);
+        if action_state.just_pressed(&PlayerAction::Grab) && !slot.ready {
+            settings.editor = if editing { None } else { Some(entity) };
+            continue;
+        }
+
+        match input {
+            Some(MenuInput::Confirm | MenuInput::Back) => {
+                slot.ready = !slot.ready;
+                if slot.ready {
+                    info!("Player {} is ready", player.player_id + 1);
+                    if editing {
+                        settings.editor = None;
+                    }
+                } else {
+                    info!("Player {} is not ready anymore", player.player_id + 1);
+                }
+            }
+            _ if slot.ready => {}
+            Some(step @ (MenuInput::Up | MenuInput::Down)) if editing => {
+                settings.selected =
+                    step_selection(settings.selected, LobbySetting::ALL.len(), step);
+            }
+            Some(step @ (MenuInput::Left | MenuInput::Right)) if editing => {
+                setting_changes.send(ChangeLobbySetting {
+                    setting: LobbySetting::ALL[settings.selected],
+                    step: match step {
+                        MenuInput::Left => SettingStep::Previous,
+                        _ => SettingStep::Next,
+                    },
+                });
+            }
+            Some(step @ (MenuInput::Left | MenuInput::Right)) => {
+                let taken_colors: Vec<usize> = colors
+                    .iter()
+                    .filter(|(other, _)| *other != entity)
+                    .map(|(_, color)| *color)
+                    .collect();
+                appearance.color = next_free_color(appearance.color, step, &taken_colors);
+                if let Some((_, color)) = colors.iter_mut().find(|(other, _)| *other == entity) {
+                    *color = appearance.color;
+                }
+            }
+            Some(step @ (MenuInput::Up | MenuInput::Down)) => {
+                appearance.character = next_character(appearance.character, step);
+            }
+            None => {}
+        }
+    }
+}
+
+// The next color in the direction of `step` that nobody else has
+fn next_free_color(current: usize, step: MenuInput, taken_colors: &[usize]) -> usize {
+    let len = PLAYER_COLORS.len();
+    (1..len)
+        .map(|offset| match step {
+            MenuInput::Left => (current + len - offset) % len,
+            _ => (current + offset) % len,
+        })
+        .find(|color| !taken_colors.contains(color))
+        .unwrap_or(current)
+}
+
+fn next_character(current: Character, step: MenuInput) -> Character {
+    let len = Character::ALL.len();
+    let index = Character::ALL
+        .iter()
+        .position(|character| *character == current)
+        .unwrap_or(0);
+    match step {
+        MenuInput::Up => Character::ALL[(index + len - 1) % len],
+        _ => Character::ALL[(index + 1) % len],
+    }
+}
+
+// Starts the match once everyone has been ready for the whole countdown.
+// Anyone joining or changing their mind calls it off
+fn count_down_to_match(
+    mut commands: Commands,
+    players: Query<(&Player, Option<&LobbySlot>)>,
+    countdown: Option<ResMut<LobbyCountdown>>,
+    mut app_state_next_state: ResMut<NextState<AppState>>,
+    time: Res<Time>,
+) {
+    // Someone has to be there besides the bots
+    let anyone_playing = players
+        .iter()
+        .any(|(player, _)| !matches!(player.input_source, InputSource::Bot(_)));
+    let everyone_ready = anyone_playing
+        && players
+            .iter()
+            .all(|(_, slot)| slot.is_some_and(|slot| slot.ready));
+
+    match (everyone_ready, countdown) {
+        (true, None) => {
+            info!("Everyone is ready, starting in {LOBBY_COUNTDOWN_SECONDS} seconds");
+            commands.insert_resource(LobbyCountdown(Timer::from_seconds(
+                LOBBY_COUNTDOWN_SECONDS,
+                TimerMode::Once,
+            )));
+        }
+        (true, Some(mut countdown)) => {
+            if countdown.0.tick(time.delta()).just_finished() {
+                app_state_next_state.set(AppState::InGame);
+            }
+        }
+        (false, Some(_)) => {
+            info!("Not everyone is ready anymore, the countdown is cancelled");
+            commands.remove_resource::<LobbyCountdown>();
+        }
+        (false, None) => {}
+    }
+}
+
+// Everyone has to ready up again the next time the lobby opens
+fn leave_lobby(
+    mut commands: Commands,
+    players: Query<Entity, With<LobbySlot>>,
+    mut settings: ResMut<LobbySettings>,
+) {
+    commands.remove_resource::<LobbyCountdown>();
+    settings.editor = None;
+    for player in players.iter() {
+        commands.entity(player).remove::<LobbySlot>();
+    }
+}
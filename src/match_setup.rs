@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    player::{PlayerReset, STARTING_LIVES},
+    resources::{CountdownTimer, JoinedPlayers, MatchSeed},
+    rng::GameRng,
+    rounds::{MatchRules, MatchScore, RoundClock},
+    stats::MatchStats,
+    AppState, GameState, BOTTOM_WALL, COLOR_FLOOR, FLOOR_THICKNESS, LEFT_WALL, RIGHT_WALL,
+    TOP_WALL,
+};
+
+// The app states and the resources and arena every match is played in.
+// Entities a match spawns are scoped to `AppState::InGame` and go away with it,
+// the resources a match uses are reset from `MatchConfig` whenever one starts
+pub struct MatchPlugin;
+
+impl Plugin for MatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<JoinedPlayers>()
+            .init_resource::<MatchConfig>()
+            // .add_event::<CollisionEvent>()
+            .add_systems(Startup, spawn_map_borders)
+            .init_state::<GameState>()
+            .init_state::<AppState>()
+            .enable_state_scoped_entities::<AppState>()
+            .configure_sets(
+                OnEnter(AppState::InGame),
+                (MatchSetup::Resources, MatchSetup::Entities).chain(),
+            )
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (
+                    reset_match_resources.in_set(MatchSetup::Resources),
+                    reset_players.in_set(MatchSetup::Entities),
+                ),
+            )
+            .add_systems(OnExit(AppState::InGame), take_ball_from_players);
+    }
+}
+
+// Steps of setting up a match when it starts
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchSetup {
+    // Resetting the resources, the rng included
+    Resources,
+    // Spawning the ball and the enemy and putting the players in place,
+    // after the rng has been seeded so the spawns are the same every match
+    Entities,
+}
+
+// What every match is started from, together with the `MatchSeed`
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MatchConfig {
+    pub rules: MatchRules,
+    pub starting_lives: u32,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            rules: MatchRules::default(),
+            starting_lives: STARTING_LIVES,
+        }
+    }
+}
+
+// Nothing of the previous match carries over, a rematch starts from the same state as the first match
+fn reset_match_resources(
+    config: Res<MatchConfig>,
+    seed: Res<MatchSeed>,
+    mut rng: ResMut<GameRng>,
+    mut countdown: ResMut<CountdownTimer>,
+    mut stats: ResMut<MatchStats>,
+    mut score: ResMut<MatchScore>,
+    mut clock: ResMut<RoundClock>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    *rng = GameRng::from_seed(seed.0);
+    *stats = MatchStats::default();
+    *score = MatchScore {
+        round: 1,
+        ..default()
+    };
+    *clock = RoundClock::for_round(&config.rules);
+    // Held until the countdown is over, however the last match was left
+    next_game_state.set(GameState::Paused);
+    countdown.start();
+    info!("Round 1 is about to start");
+}
+
+// Players stay joined between matches, so they are put back on their feet here
+fn reset_players(mut player_reset: PlayerReset, config: Res<MatchConfig>) {
+    player_reset.reset_all(config.starting_lives);
+    player_reset.clear_scores();
+}
+
+// The ball goes away with the match, nobody can be left holding it
+fn take_ball_from_players(mut player_reset: PlayerReset) {
+    player_reset.take_ball();
+}
+
+fn spawn_map_borders(mut commands: Commands) {
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: COLOR_FLOOR,
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: Vec3::new(0.0, BOTTOM_WALL + (FLOOR_THICKNESS / 2.0), 0.0),
+                scale: Vec3::new(1280.0, FLOOR_THICKNESS, 1.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RigidBody::Fixed)
+        .insert(Collider::cuboid(0.5, 0.5));
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: COLOR_FLOOR,
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: Vec3::new(0.0, TOP_WALL + (FLOOR_THICKNESS / 2.0), 0.0),
+                scale: Vec3::new(1280.0, FLOOR_THICKNESS, 1.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RigidBody::Fixed)
+        .insert(Collider::cuboid(0.5, 0.5));
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: COLOR_FLOOR,
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: Vec3::new(LEFT_WALL + (FLOOR_THICKNESS / 2.0), 0.0, 0.0),
+                scale: Vec3::new(FLOOR_THICKNESS, 1024.0, 1.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RigidBody::Fixed)
+        .insert(Collider::cuboid(0.5, 0.5));
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: COLOR_FLOOR,
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: Vec3::new(RIGHT_WALL + (FLOOR_THICKNESS / 2.0), 0.0, 0.0),
+                scale: Vec3::new(FLOOR_THICKNESS, 1024.0, 1.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RigidBody::Fixed)
+        .insert(Collider::cuboid(0.5, 0.5));
+}
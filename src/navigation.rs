@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::gamepad::PlayerAction;
+
+// How far the stick has to be pushed to move the selection
+const STICK_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    // Dash
+    Confirm,
+    // Start
+    Back,
+}
+
+// Reads menus through the player's own bindings, so keyboards and gamepads work alike.
+// `stick_tilted` keeps whether the stick was already pushed, holding it only moves one step
+pub fn read_menu_input(
+    action_state: &ActionState<PlayerAction>,
+    stick_tilted: &mut bool,
+) -> Option<MenuInput> {
+    let stick = action_state
+        .clamped_axis_pair(&PlayerAction::Move)
+        .map_or(Vec2::ZERO, |axis_pair| axis_pair.xy());
+    let tilted = stick.abs().max_element() > STICK_THRESHOLD;
+    let stick_moved = tilted && !*stick_tilted;
+    *stick_tilted = tilted;
+    // The stick counts along whichever axis it is pushed further
+    let vertical = stick.y.abs() >= stick.x.abs();
+
+    if action_state.just_pressed(&PlayerAction::Up) || (stick_moved && vertical && stick.y > 0.0) {
+        Some(MenuInput::Up)
+    } else if action_state.just_pressed(&PlayerAction::Down)
+        || (stick_moved && vertical && stick.y < 0.0)
+    {
+        Some(MenuInput::Down)
+    } else if action_state.just_pressed(&PlayerAction::Left)
+        || (stick_moved && !vertical && stick.x < 0.0)
+    {
+        Some(MenuInput::Left)
+    } else if action_state.just_pressed(&PlayerAction::Right)
+        || (stick_moved && !vertical && stick.x > 0.0)
+    {
+        Some(MenuInput::Right)
+    } else if action_state.just_pressed(&PlayerAction::Dash) {
+        Some(MenuInput::Confirm)
+    } else if action_state.just_pressed(&PlayerAction::Start) {
+        Some(MenuInput::Back)
+    } else {
+        None
+    }
+}
+
+// Moves the selection of a list with `len` entries, wrapping around at both ends
+pub fn step_selection(selected: usize, len: usize, input: MenuInput) -> usize {
+    match input {
+        MenuInput::Up => selected.checked_sub(1).unwrap_or(len - 1),
+        MenuInput::Down => (selected + 1) % len,
+        MenuInput::Left | MenuInput::Right | MenuInput::Confirm | MenuInput::Back => selected,
+    }
+}
@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::gamepad::{leave_disconnected_players, AwaitingReconnect, PlayerAction};
+use crate::navigation::{read_menu_input, step_selection, MenuInput};
+use crate::player::Player;
+use crate::resources::{CountdownTimer, PauseMenu};
+use crate::rounds::{ResetRound, RoundClock};
+use crate::{AppState, GameState};
+
+// Any player's Start pauses the match and opens the pause menu, which only the
+// player who paused controls. `UiPlugin` only draws the menu
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OpenSettings>()
+            // While a controller is missing Start means going on without it instead,
+            // the pause menu has to see the missing player before they are gone
+            .configure_sets(
+                Update,
+                PauseSet
+                    .run_if(in_state(AppState::InGame))
+                    .before(leave_disconnected_players),
+            )
+            .add_systems(Update, pause_menu_input.in_set(PauseSet))
+            .add_systems(OnExit(AppState::InGame), leave_pause_menu);
+    }
+}
+
+// The pause menu systems, anything that draws the menu runs after them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PauseSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseOption {
+    Resume,
+    RestartRound,
+    Settings,
+    QuitToMenu,
+}
+
+pub const PAUSE_OPTIONS: [PauseOption; 4] = [
+    PauseOption::Resume,
+    PauseOption::RestartRound,
+    PauseOption::Settings,
+    PauseOption::QuitToMenu,
+];
+
+impl PauseOption {
+    pub fn label(self) -> &'static str {
+        match self {
+            PauseOption::Resume => "Resume",
+            PauseOption::RestartRound => "Restart Round",
+            PauseOption::Settings => "Settings",
+            PauseOption::QuitToMenu => "Quit to Menu",
+        }
+    }
+
+    // The next round is already on its way during the intermission,
+    // restarting the one that just ended would play it a second time
+    pub fn is_enabled(self, clock: &RoundClock) -> bool {
+        match self {
+            PauseOption::RestartRound => !clock.in_intermission(),
+            _ => true,
+        }
+    }
+}
+
+// Sent when the player who paused picks Settings. Changing controls needs the
+// controls screen, so it is up to the UI
+#[derive(Event, Debug, Clone, Copy)]
+pub struct OpenSettings {
+    pub player: Entity,
+}
+
+// Any player's Start opens the menu, after that only the player who paused controls it.
+// While a controller is missing Start means going on without it, see `leave_disconnected_players`
+fn pause_menu_input(
+    mut commands: Commands,
+    menu: Option<ResMut<PauseMenu>>,
+    players: Query<(Entity, &Player, &ActionState<PlayerAction>)>,
+    awaiting_reconnect: Query<(), With<AwaitingReconnect>>,
+    clock: Res<RoundClock>,
+    mut countdown: ResMut<CountdownTimer>,
+    mut reset_round: EventWriter<ResetRound>,
+    mut open_settings: EventWriter<OpenSettings>,
+) {
+    if !awaiting_reconnect.is_empty() {
+        return;
+    }
+
+    let Some(mut menu) = menu else {
+        let Some((entity, player, _)) = players
+            .iter()
+            .find(|(_, _, action_state)| action_state.just_pressed(&PlayerAction::Start))
+        else {
+            return;
+        };
+
+        info!("Player {} paused the match", player.player_id + 1);
+        commands.insert_resource(PauseMenu {
+            player: entity,
+            player_id: player.player_id,
+            selected: 0,
+            stick_tilted: true,
+        });
+        commands.insert_resource(NextState::Pending(GameState::Paused));
+        // Stop a countdown that is already running from resuming the match
+        countdown.stop();
+        return;
+    };
+
+    // The player who paused may have left in the meantime
+    let Ok((_, player, action_state)) = players.get(menu.player) else {
+        resume(&mut commands, &clock, &mut countdown);
+        return;
+    };
+
+    // Only written when it changes, any change redraws the menu
+    let mut stick_tilted = menu.stick_tilted;
+    let input = read_menu_input(action_state, &mut stick_tilted);
+    if menu.stick_tilted != stick_tilted {
+        menu.stick_tilted = stick_tilted;
+    }
+
+    match input {
+        Some(step @ (MenuInput::Up | MenuInput::Down)) => {
+            menu.selected = step_selection(menu.selected, PAUSE_OPTIONS.len(), step);
+        }
+        Some(MenuInput::Back) => resume(&mut commands, &clock, &mut countdown),
+        Some(MenuInput::Confirm) if !PAUSE_OPTIONS[menu.selected].is_enabled(&clock) => {}
+        Some(MenuInput::Confirm) => match PAUSE_OPTIONS[menu.selected] {
+            PauseOption::Resume => resume(&mut commands, &clock, &mut countdown),
+            PauseOption::RestartRound => {
+                info!("Player {} restarted the round", player.player_id + 1);
+                commands.remove_resource::<PauseMenu>();
+                reset_round.send(ResetRound);
+            }
+            PauseOption::Settings => {
+                open_settings.send(OpenSettings {
+                    player: menu.player,
+                });
+            }
+            PauseOption::QuitToMenu => {
+                info!("Player {} quit the match", player.player_id + 1);
+                commands.remove_resource::<PauseMenu>();
+                commands.insert_resource(NextState::Pending(AppState::Menu));
+            }
+        },
+        Some(MenuInput::Left | MenuInput::Right) | None => {}
+    }
+}
+
+// Goes back to the match through the countdown. Between rounds the
+// intermission starts the countdown once it is over
+fn resume(commands: &mut Commands, clock: &RoundClock, countdown: &mut CountdownTimer) {
+    commands.remove_resource::<PauseMenu>();
+    if !clock.in_intermission() {
+        countdown.start();
+    }
+}
+
+// A match that ends while paused doesn't leave the menu open for the next one
+fn leave_pause_menu(mut commands: Commands) {
+    commands.remove_resource::<PauseMenu>();
+}
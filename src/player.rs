@@ -1,25 +1,35 @@
+use crate::ball::{spawn_loose_ball, PossessionCause, PossessionChanged};
+use crate::direction_indicator::DirectionIndicator;
 use crate::enemy::Enemy;
+use crate::feedback::RumbleIntensity;
 use crate::gamepad::PlayerAction;
-use crate::resources::CountdownTimer;
+use crate::grab::Grab;
+use crate::input_buffer::{FixedTick, InputBuffer};
+use crate::resources::{CountdownTimer, InputSource, JoinedPlayers};
+use crate::revive::{down_player, Downed};
+use crate::shield::{Shield, ShieldBlocked};
+use crate::simulation::{GameplaySet, InterpolatedTranslation};
 use crate::sprites::{AnimationIndices, AnimationTimer};
-use crate::{AppState, GameState, BOTTOM_WALL, LEFT_WALL, RIGHT_WALL, TOP_WALL, WALL_THICKNESS};
+use crate::tuning::Tuning;
+use crate::GameState;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-const PLAYER_SPEED: f32 = 500.0;
-const PLAYER_PADDING: f32 = 10.0;
-const PLAYER_SIZE: Vec2 = Vec2::new(5.0, 8.0);
+pub const STARTING_LIVES: u32 = 5;
+pub const SPAWN_POSITION: Vec3 = Vec3::new(50.0, -250.0, 2.0);
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (move_player, collision_with_enemy)
-                .run_if(in_state(AppState::InGame))
-                .run_if(in_state(GameState::Running)),
+        app.add_event::<PlayerHit>().add_systems(
+            FixedUpdate,
+            (
+                move_player.in_set(GameplaySet::Movement),
+                collision_with_enemy.in_set(GameplaySet::Collision),
+            ),
         );
     }
 }
@@ -29,10 +39,17 @@ pub struct PlayerBundle {
     pub player: Player,
     pub direction: PlayerDirection,
     pub velocity: Velocity,
-    pub sprite: SpriteSheetBundle,
+    pub spatial: SpatialBundle,
     pub animation_indices: AnimationIndices,
     pub animation_timer: AnimationTimer,
     pub input_manager: InputManagerBundle<PlayerAction>,
+    pub shield: Shield,
+    pub grab: Grab,
+    pub input_buffer: InputBuffer,
+    pub dash_cooldown: DashCooldown,
+    pub rumble_intensity: RumbleIntensity,
+    pub interpolated_translation: InterpolatedTranslation,
+    pub appearance: Appearance,
 }
 
 impl Default for PlayerBundle {
@@ -40,22 +57,30 @@ impl Default for PlayerBundle {
         Self {
             player: Player {
                 player_id: 0,
-                lives: 3,
-                gamepad: Gamepad { id: 0 },
+                lives: STARTING_LIVES,
+                input_source: InputSource::Gamepad(Gamepad { id: 0 }),
                 have_ball: false,
+                score: 0,
             },
             direction: PlayerDirection {
                 direction: Vec2::new(0.0, 0.0),
             },
             velocity: Velocity(Vec2::new(0.0, 0.0)),
             input_manager: InputManagerBundle::default(),
-            sprite: SpriteSheetBundle::default(),
+            spatial: SpatialBundle::default(),
             //Idle animation
             animation_indices: AnimationIndices {
                 first: 10,
                 last: 13,
             },
             animation_timer: AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            shield: Shield::default(),
+            grab: Grab::default(),
+            input_buffer: InputBuffer::default(),
+            dash_cooldown: DashCooldown(Timer::from_seconds(0.0, TimerMode::Once)),
+            rumble_intensity: RumbleIntensity::default(),
+            interpolated_translation: InterpolatedTranslation::new(Vec3::ZERO),
+            appearance: Appearance::default(),
         }
     }
 }
@@ -64,66 +89,120 @@ impl Default for PlayerBundle {
 pub struct Player {
     pub player_id: usize,
     pub lives: u32,
-    pub gamepad: Gamepad,
+    pub input_source: InputSource,
     pub have_ball: bool,
+    pub score: u32,
+}
+
+impl Player {
+    pub fn gamepad(&self) -> Option<Gamepad> {
+        match self.input_source {
+            InputSource::Gamepad(gamepad) => Some(gamepad),
+            _ => None,
+        }
+    }
+}
+
+// Sent when the enemy catches a player without a raised shield
+#[derive(Event, Debug)]
+pub struct PlayerHit {
+    pub player: Entity,
 }
 
 #[derive(Component)]
 pub struct Velocity(pub Vec2);
 
+// Colors players can pick in the lobby, by name. The sprite is tinted with them
+pub const PLAYER_COLORS: [(&str, Color); 6] = [
+    ("White", Color::WHITE),
+    ("Red", Color::srgb(1.0, 0.45, 0.45)),
+    ("Blue", Color::srgb(0.45, 0.6, 1.0)),
+    ("Green", Color::srgb(0.5, 1.0, 0.5)),
+    ("Yellow", Color::srgb(1.0, 0.95, 0.4)),
+    ("Purple", Color::srgb(0.8, 0.5, 1.0)),
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Character {
+    #[default]
+    Ducky,
+    Chicken,
+}
+
+impl Character {
+    pub const ALL: [Character; 2] = [Character::Ducky, Character::Chicken];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Character::Ducky => "Ducky",
+            Character::Chicken => "Chicken",
+        }
+    }
+}
+
+// Picked in the lobby, only changes how the player is drawn
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Appearance {
+    // Index into `PLAYER_COLORS`
+    pub color: usize,
+    pub character: Character,
+}
+
+impl Appearance {
+    pub fn color(&self) -> Color {
+        PLAYER_COLORS[self.color % PLAYER_COLORS.len()].1
+    }
+
+    pub fn color_name(&self) -> &'static str {
+        PLAYER_COLORS[self.color % PLAYER_COLORS.len()].0
+    }
+}
+
 #[derive(Component)]
 pub struct PlayerDirection {
     pub direction: Vec2,
 }
 
+#[derive(Component)]
+pub struct DashCooldown(pub Timer);
+
+// Only the simulated part of the player, `PresentationPlugin` adds the sprites when there is a window
 pub fn spawn_player(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    texture_atlases_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
     input_map: InputMap<PlayerAction>,
-    gamepad: Gamepad,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
+    input_source: InputSource,
+    player_id: usize,
 ) -> Entity {
-    let texture = asset_server.load("duckyatlas.png");
-    // let texture_atlas =
-    //     TextureAtlas::from_grid(texture_handle, Vec2::new(64.0, 64.0), 5, 3, None, None);
-    let layout = TextureAtlasLayout::from_grid(UVec2::new(64, 64), 5, 3, None, None);
-    let texture_atlas_layout = texture_atlases_layouts.add(layout);
-    // Use only the subset of sprites in the sheet that make up the run animation
-    let animation_indices = AnimationIndices {
-        first: 10,
-        last: 13,
-    };
-
     let player = commands
         .spawn(PlayerBundle {
             player: Player {
-                player_id: gamepad.id, //make this dynamic
-                lives: 3,
-                gamepad,
+                player_id,
+                lives: STARTING_LIVES,
+                input_source,
                 have_ball: false,
+                score: 0,
             },
             input_manager: InputManagerBundle {
                 input_map,
                 ..Default::default()
             },
-            sprite: SpriteSheetBundle {
-                // texture_atlas: texture_atlas_handle,
-                // sprite: Sprite::new(animation_indices.first),
-                texture,
-                atlas: TextureAtlas {
-                    layout: texture_atlas_layout,
-                    index: animation_indices.first,
-                },
-                transform: Transform::from_xyz(50.0, -250., 2.0),
+            spatial: SpatialBundle::from_transform(Transform::from_translation(SPAWN_POSITION)),
+            interpolated_translation: InterpolatedTranslation::new(SPAWN_POSITION),
+            // Everyone starts out in a different color, as long as there are enough
+            appearance: Appearance {
+                color: player_id % PLAYER_COLORS.len(),
                 ..default()
             },
             ..default()
         })
         .insert(RigidBody::KinematicPositionBased)
         .insert(Ccd::enabled())
-        .insert(KinematicCharacterController::default())
+        .insert(KinematicCharacterController {
+            // Walk into the ball and the enemy so their contacts still register,
+            // but slide along walls, props and other players
+            filter_flags: QueryFilterFlags::EXCLUDE_DYNAMIC,
+            ..default()
+        })
         .insert(Collider::ball(10.0))
         .insert(ActiveEvents::COLLISION_EVENTS)
         .insert(GravityScale(0.0))
@@ -132,99 +211,220 @@ pub fn spawn_player(
     return player;
 }
 
+// Removes a player from the match, dropping the ball where they stood if they were carrying it
+pub fn despawn_player(
+    commands: &mut Commands,
+    player_entity: Entity,
+    player: &Player,
+    position: Vec2,
+    joined_players: &mut ResMut<JoinedPlayers>,
+    possession_changed: &mut EventWriter<PossessionChanged>,
+) {
+    if player.have_ball {
+        spawn_loose_ball(commands, position);
+        possession_changed.send(PossessionChanged {
+            from: Some(player_entity),
+            to: None,
+            cause: PossessionCause::Drop,
+        });
+    }
+
+    // The direction indicator is a child, so it goes with the player
+    commands.entity(player_entity).despawn_recursive();
+    joined_players.0.remove(&player.input_source);
+}
+
+// Puts the joined players back the way a match starts, for a new match and for every round
+#[derive(SystemParam)]
+pub struct PlayerReset<'w, 's> {
+    commands: Commands<'w, 's>,
+    players: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Player,
+            &'static mut Transform,
+            &'static mut InterpolatedTranslation,
+            &'static mut Velocity,
+        ),
+        Without<Enemy>,
+    >,
+    indicators: Query<'w, 's, Entity, With<DirectionIndicator>>,
+    possession_changed: EventWriter<'w, PossessionChanged>,
+}
+
+impl PlayerReset<'_, '_> {
+    // Takes the ball away from whoever carries it. The ball itself isn't
+    // spawned again, that is up to the caller
+    pub fn take_ball(&mut self) {
+        for (entity, mut player, ..) in self.players.iter_mut() {
+            if player.have_ball {
+                player.have_ball = false;
+                self.possession_changed.send(PossessionChanged {
+                    from: Some(entity),
+                    to: None,
+                    cause: PossessionCause::Drop,
+                });
+            }
+        }
+        for indicator in self.indicators.iter() {
+            self.commands.entity(indicator).despawn_recursive();
+        }
+    }
+
+    // Back on their feet at the spawn point with `lives` and without the ball
+    pub fn reset_all(&mut self, lives: u32) {
+        self.take_ball();
+        for (entity, mut player, mut transform, mut interpolated, mut velocity) in
+            self.players.iter_mut()
+        {
+            player.lives = lives;
+            self.commands.entity(entity).remove::<Downed>();
+            transform.translation = SPAWN_POSITION;
+            *interpolated = InterpolatedTranslation::new(SPAWN_POSITION);
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+
+    pub fn clear_scores(&mut self) {
+        for (_, mut player, ..) in self.players.iter_mut() {
+            player.score = 0;
+        }
+    }
+}
+
 fn move_player(
     mut query: Query<
         (
+            &Player,
+            &Shield,
             &ActionState<PlayerAction>,
-            &mut Transform,
+            &mut KinematicCharacterController,
             &mut Velocity,
             &mut PlayerDirection,
-            &mut Sprite,
-            &mut TextureAtlas,
+            &mut InputBuffer,
+            &mut DashCooldown,
         ),
-        With<Player>,
+        Without<Downed>,
     >,
-    time_step: Res<Time<Fixed>>,
+    tuning: Res<Tuning>,
+    tick: Res<FixedTick>,
+    time: Res<Time>,
 ) {
+    let movement = tuning.movement;
+    let delta = time.delta_seconds();
+
     for (
+        player,
+        shield,
         action_state,
-        mut player_transform,
+        mut controller,
         mut velocity,
         mut direction,
-        mut sprite,
-        mut texture_atlas,
+        mut input_buffer,
+        mut dash_cooldown,
     ) in query.iter_mut()
     {
-        let mut horizontal = 0.0;
-        let mut vertical = 0.0;
+        let mut input = Vec2::ZERO;
         if action_state.pressed(&PlayerAction::Up) {
-            vertical += 1.0;
+            input.y += 1.0;
         }
         if action_state.pressed(&PlayerAction::Down) {
-            vertical -= 1.0;
+            input.y -= 1.0;
         }
         if action_state.pressed(&PlayerAction::Left) {
-            horizontal -= 1.0;
-            sprite.flip_x = true;
+            input.x -= 1.0;
         }
         if action_state.pressed(&PlayerAction::Right) {
-            horizontal += 1.0;
-            sprite.flip_x = false;
+            input.x += 1.0;
         }
 
-        if action_state.pressed(&PlayerAction::Dash) {
-            info!("dashed pressesd {:?}", player_transform);
-        }
-
-        let mut new_player_position_horizontal =
-            player_transform.translation.x + horizontal * PLAYER_SPEED * time_step.delta_seconds();
-
-        let mut new_player_position_vertical =
-            player_transform.translation.y + vertical * PLAYER_SPEED * time_step.delta_seconds();
-
         if action_state.pressed(&PlayerAction::Move) {
             // We're working with gamepads, so we want to defensively ensure that we're using the clamped values
             let axis_pair = action_state.clamped_axis_pair(&PlayerAction::Move).unwrap();
+            input += axis_pair.xy();
+        }
 
-            velocity.0.x = axis_pair.x();
-            velocity.0.y = axis_pair.y();
-            direction.direction.x = axis_pair.x();
-            direction.direction.y = axis_pair.y();
+        // Keys and stick add up, so cap the length to keep diagonals as fast as straight lines
+        // while still allowing partial stick deflection to walk slower
+        let input = input.clamp_length_max(1.0);
 
-            new_player_position_horizontal = player_transform.translation.x
-                + velocity.0.x * PLAYER_SPEED * time_step.delta_seconds();
-            new_player_position_vertical = player_transform.translation.y
-                + velocity.0.y * PLAYER_SPEED * time_step.delta_seconds();
+        let mut top_speed = movement.top_speed;
+        if player.have_ball {
+            top_speed *= movement.ball_carry_speed_factor;
+        }
+        if shield.is_raised() {
+            top_speed *= tuning.shield.move_speed_factor;
+        }
 
-            // if moved left or right flip sprite
-            if velocity.0.x != 0.0 {
-                sprite.flip_x = velocity.0.x < 0.0;
-            }
+        velocity.0 = if input != Vec2::ZERO {
+            direction.direction = input;
+            approach(velocity.0, input * top_speed, movement.acceleration * delta)
+        } else {
+            approach(velocity.0, Vec2::ZERO, movement.deceleration * delta)
+        };
 
-            // idle animation or run animation
-            if velocity.0.x != 0.0 || velocity.0.y != 0.0 {
-                if texture_atlas.index < 4 || texture_atlas.index > 7 {
-                    texture_atlas.index = 4;
-                }
+        // A dash pressed while the cooldown is still running fires as soon as it is ready.
+        // The burst is above the top speed and the acceleration brings it back down
+        dash_cooldown.0.tick(time.delta());
+        if dash_cooldown.0.finished()
+            && input_buffer.consume(PlayerAction::Dash, tick.0, tuning.input_buffer.dash_window)
+        {
+            let dash_direction = if input != Vec2::ZERO {
+                input.normalize()
             } else {
-                if texture_atlas.index < 10 || texture_atlas.index > 13 {
-                    texture_atlas.index = 10;
-                }
+                direction.direction.normalize_or_zero()
+            };
+            velocity.0 = dash_direction * movement.dash_speed;
+            dash_cooldown.0 = Timer::from_seconds(movement.dash_cooldown, TimerMode::Once);
+        }
+
+        // Let the character controller resolve the move against the arena colliders,
+        // so the player slides along walls and obstacles instead of passing through them
+        controller.translation = Some(velocity.0 * delta);
+    }
+}
+
+pub fn update_player_sprite(
+    mut query: Query<
+        (
+            &Velocity,
+            &PlayerDirection,
+            &mut Sprite,
+            Option<&mut TextureAtlas>,
+        ),
+        With<Player>,
+    >,
+) {
+    for (velocity, direction, mut sprite, texture_atlas) in query.iter_mut() {
+        // if moved left or right flip sprite
+        if direction.direction.x != 0.0 {
+            sprite.flip_x = direction.direction.x < 0.0;
+        }
+
+        // Only characters drawn from a sprite sheet are animated
+        let Some(mut texture_atlas) = texture_atlas else {
+            continue;
+        };
+        // idle animation or run animation
+        if velocity.0 != Vec2::ZERO {
+            if texture_atlas.index < 4 || texture_atlas.index > 7 {
+                texture_atlas.index = 4;
             }
+        } else if texture_atlas.index < 10 || texture_atlas.index > 13 {
+            texture_atlas.index = 10;
         }
+    }
+}
 
-        // Update the player position,
-        // making sure it doesn't cause the player to leave the arena
-        let left_bound = LEFT_WALL + WALL_THICKNESS / 2.0 + PLAYER_SIZE.x / 2.0 + PLAYER_PADDING;
-        let right_bound = RIGHT_WALL - WALL_THICKNESS / 2.0 - PLAYER_SIZE.x / 2.0 - PLAYER_PADDING;
-        let top_bound = TOP_WALL - WALL_THICKNESS / 2.0 - PLAYER_SIZE.y / 2.0 - PLAYER_PADDING;
-        let bottom_bound =
-            BOTTOM_WALL + WALL_THICKNESS / 2.0 + PLAYER_SIZE.y / 2.0 + PLAYER_PADDING;
-
-        player_transform.translation.x =
-            new_player_position_horizontal.clamp(left_bound, right_bound);
-        player_transform.translation.y =
-            new_player_position_vertical.clamp(bottom_bound, top_bound);
+// Moves `current` towards `target` by at most `max_delta`
+fn approach(current: Vec2, target: Vec2, max_delta: f32) -> Vec2 {
+    let difference = target - current;
+    if difference.length() <= max_delta {
+        target
+    } else {
+        current + difference.normalize() * max_delta
     }
 }
 
@@ -235,30 +435,53 @@ fn move_player(
 // ) {
 // }
 
-fn collision_with_enemy(
+pub fn collision_with_enemy(
     mut commands: Commands,
     enemy_query: Query<Entity, With<Enemy>>,
-    mut player_query: Query<(Entity, &Player), With<Player>>,
+    mut player_query: Query<(Entity, &mut Player, &mut Shield), Without<Downed>>,
     rapier_context: Res<RapierContext>,
-    mut p2_lives: ResMut<crate::Player2Lives>,
-    mut p1_lives: ResMut<crate::Player1Lives>,
     mut countdown: ResMut<CountdownTimer>,
+    mut shield_blocked: EventWriter<ShieldBlocked>,
+    mut player_hit: EventWriter<PlayerHit>,
+    next_game_state: Res<NextState<GameState>>,
+    tuning: Res<Tuning>,
 ) {
-    let enemy = enemy_query.single();
+    // A frame can run several ticks before the pause from a hit takes effect,
+    // the contact that caused it must not count again on those
+    if matches!(*next_game_state, NextState::Pending(GameState::Paused)) {
+        return;
+    }
+    let Ok(enemy) = enemy_query.get_single() else {
+        return;
+    };
 
-    for (entity, player) in player_query.iter_mut() {
+    for (entity, mut player, mut shield) in player_query.iter_mut() {
         if let Some(contact_pair) = rapier_context.contact_pair(entity, enemy) {
             if contact_pair.has_any_active_contact() {
+                // A raised shield absorbs the hit instead of costing a life
+                if shield.is_raised() {
+                    shield.lower(tuning.shield.cooldown);
+                    shield_blocked.send(ShieldBlocked {
+                        player: entity,
+                        enemy,
+                    });
+                    continue;
+                }
+
                 commands.insert_resource(NextState::Pending(GameState::Paused));
+                player_hit.send(PlayerHit { player: entity });
 
-                if player.player_id == 0 {
-                    p1_lives.lives -= 1;
-                    println!("Player 1 has {} lives left", p1_lives.lives);
-                } else {
-                    p2_lives.lives -= 1;
-                    println!("Player 2 has {} lives left", p2_lives.lives);
+                player.lives = player.lives.saturating_sub(1);
+                info!(
+                    "Player {} has {} lives left",
+                    player.player_id + 1,
+                    player.lives
+                );
+                if player.lives == 0 {
+                    info!("Player {} is down", player.player_id + 1);
+                    down_player(&mut commands, entity, tuning.revive.bleed_out);
                 }
-                countdown.duration = 4;
+                countdown.start();
             }
         }
     }
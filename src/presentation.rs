@@ -0,0 +1,168 @@
+use bevy::{prelude::*, sprite::Mesh2dHandle, transform::TransformSystem};
+
+use crate::{
+    ball::Ball,
+    call_for_pass::{show_calls, spawn_call_text},
+    direction_indicator::DirectionIndicator,
+    enemy::Enemy,
+    grab::{show_grab, spawn_grab_sprite},
+    player::{update_player_sprite, Appearance, Character, Player},
+    revive::tint_downed_players,
+    shield::{show_shield, spawn_shield_sprite},
+    simulation::interpolate_translation,
+    AppState, GameState,
+};
+
+// Everything that is only needed to look at the game. The simulation spawns
+// bare entities, and the systems here give them sprites and meshes, so the
+// game plugins also run headless without any render assets
+pub struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_camera)
+            .add_systems(
+                Update,
+                (
+                    add_player_sprites,
+                    show_player_appearance,
+                    add_enemy_sprite,
+                    add_ball_mesh,
+                    add_indicator_mesh,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_player_sprite,
+                    show_shield,
+                    show_grab,
+                    show_calls,
+                    tint_downed_players,
+                )
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Running)),
+            )
+            .add_systems(
+                PostUpdate,
+                interpolate_translation.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+}
+
+fn add_player_sprites(
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    for player in players.iter() {
+        commands.entity(player).with_children(|parent| {
+            spawn_shield_sprite(parent, &asset_server, &mut texture_atlases_layouts);
+            spawn_grab_sprite(parent, &asset_server, &mut texture_atlases_layouts);
+            spawn_call_text(parent, &asset_server);
+        });
+    }
+}
+
+// Draws the character and color picked in the lobby, and again whenever they change
+fn show_player_appearance(
+    mut commands: Commands,
+    players: Query<(Entity, &Appearance), (With<Player>, Changed<Appearance>)>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    // Every ducky shares one layout, made the first time one is drawn
+    mut ducky_layout: Local<Option<Handle<TextureAtlasLayout>>>,
+) {
+    for (player, appearance) in players.iter() {
+        let sprite = Sprite {
+            color: appearance.color(),
+            ..default()
+        };
+        match appearance.character {
+            Character::Ducky => {
+                let layout = ducky_layout.get_or_insert_with(|| {
+                    texture_atlases_layouts.add(TextureAtlasLayout::from_grid(
+                        UVec2::new(64, 64),
+                        5,
+                        3,
+                        None,
+                        None,
+                    ))
+                });
+                commands.entity(player).insert((
+                    sprite,
+                    asset_server.load::<Image>("duckyatlas.png"),
+                    TextureAtlas {
+                        layout: layout.clone(),
+                        // Idle animation
+                        index: 10,
+                    },
+                ));
+            }
+            // A single image without animations, drawn about as big as the ducky
+            Character::Chicken => {
+                commands.entity(player).remove::<TextureAtlas>().insert((
+                    Sprite {
+                        custom_size: Some(Vec2::splat(48.0)),
+                        ..sprite
+                    },
+                    asset_server.load::<Image>("chicken1.png"),
+                ));
+            }
+        }
+    }
+}
+
+fn add_enemy_sprite(
+    mut commands: Commands,
+    enemies: Query<Entity, Added<Enemy>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    for enemy in enemies.iter() {
+        let layout = TextureAtlasLayout::from_grid(UVec2::new(64, 64), 6, 5, None, None);
+
+        commands.entity(enemy).insert((
+            Sprite::default(),
+            asset_server.load::<Image>("monsters/tooth-walker/toothwalker-sheet.png"),
+            TextureAtlas {
+                layout: texture_atlases_layouts.add(layout),
+                index: 0,
+            },
+        ));
+    }
+}
+
+// TODO - add ball sprite
+fn add_ball_mesh(
+    mut commands: Commands,
+    balls: Query<Entity, Added<Ball>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for ball in balls.iter() {
+        commands.entity(ball).insert((
+            Mesh2dHandle(meshes.add(Circle::new(6.))),
+            materials.add(ColorMaterial::from(Color::BLACK)),
+        ));
+    }
+}
+
+fn add_indicator_mesh(
+    mut commands: Commands,
+    indicators: Query<Entity, Added<DirectionIndicator>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for indicator in indicators.iter() {
+        commands.entity(indicator).insert((
+            Mesh2dHandle(meshes.add(Circle { radius: 4.0 })),
+            materials.add(Color::srgb(255.0, 99.0, 71.0)),
+        ));
+    }
+}
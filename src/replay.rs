@@ -0,0 +1,456 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use leafwing_input_manager::{action_state::ActionData, buttonlike::ButtonState, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    gamepad::PlayerAction,
+    input_buffer::{buffer_just_pressed, FixedInputSet, FixedTick, InputBuffer},
+    match_setup::MatchConfig,
+    player::{spawn_player, Player},
+    resources::{CurrentMap, InputSource, JoinedPlayers, MatchSeed},
+    tuning::{Tuning, TuningLoaderError, TuningOverride},
+    AppState,
+};
+
+const REPLAY_DIRECTORY: &str = "replays";
+const PLAYBACK_SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED_INDEX: usize = 2;
+// Playback speed while the fast forward key is held
+const SCRUB_SPEED: f32 = 16.0;
+// How far back the rewind key jumps
+const SEEK_BACK_TICKS: usize = 5 * 64;
+// Playback speed while the match is played again up to where the rewind jumped to.
+// The virtual clock's max delta is what really limits it
+const SEEK_SPEED: f32 = 64.0;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        // `--replay <file>` plays a recorded match back instead of starting a new one
+        let replay = replay_path_from_args().and_then(|path| match Replay::load(&path) {
+            Ok(replay) => Some(replay),
+            Err(error) => {
+                error!("{error}, starting a normal game instead");
+                None
+            }
+        });
+
+        match replay {
+            Some(replay) => play_back(app, replay),
+            None => {
+                app.add_systems(OnEnter(AppState::InGame), start_recording)
+                    .add_systems(
+                        FixedPreUpdate,
+                        record_inputs
+                            .after(FixedInputSet)
+                            .run_if(resource_exists::<ReplayRecording>),
+                    )
+                    .add_systems(OnExit(AppState::InGame), save_recording);
+            }
+        }
+    }
+}
+
+// Sets `app` up to play `replay` back from the menu instead of letting anyone join.
+// Also used by tests, which have no command line to pass the replay on
+pub fn play_back(app: &mut App, replay: Replay) {
+    app.insert_resource(MatchSeed(replay.seed))
+        .insert_resource(CurrentMap(replay.map.clone()))
+        .insert_resource(replay.config)
+        .insert_resource(TuningOverride(replay.tuning.clone()))
+        .insert_resource(ReplayPlayback::new(replay))
+        // The menu is where playback starts from, and where a rewind goes back to
+        .add_systems(OnEnter(AppState::Menu), start_playback)
+        .add_systems(
+            FixedPreUpdate,
+            (spawn_replay_players, play_back_inputs)
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(Update, (seek_backwards, control_playback).chain());
+}
+
+// Everything needed to play a match back: the starting conditions and the
+// input of every player for every fixed tick
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub map: String,
+    // Replays from before these were stored play back with the defaults
+    #[serde(default)]
+    pub config: MatchConfig,
+    #[serde(default)]
+    pub tuning: Tuning,
+    pub players: Vec<ReplayPlayer>,
+    // Indexed by fixed tick, counting from the start of the match
+    pub ticks: Vec<ReplayTick>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayPlayer {
+    pub player_id: usize,
+    pub input_source: InputSource,
+    // Fixed tick the player joined on, 0 for players that were there from the start
+    pub joined_at: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ReplayTick {
+    pub inputs: Vec<PlayerInput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlayerInput {
+    pub player_id: usize,
+    // Only the actions that are not simply released are stored
+    pub actions: Vec<RecordedAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedAction {
+    pub action: PlayerAction,
+    pub state: ButtonState,
+    pub value: f32,
+    pub axis_pair: Option<Vec2>,
+    pub held_for: Duration,
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Could not access replay: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse replay: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("Could not write replay: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("Could not use the tuning of the replay: {0}")]
+    Tuning(#[from] TuningLoaderError),
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let contents = fs::read_to_string(path)?;
+        let replay: Replay = ron::from_str(&contents)?;
+        replay.tuning.validate()?;
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+impl PlayerInput {
+    pub fn capture(player_id: usize, action_state: &ActionState<PlayerAction>) -> Self {
+        let mut actions: Vec<RecordedAction> = action_state
+            .all_action_data()
+            .iter()
+            .filter(|(_, data)| {
+                data.state != ButtonState::Released || data.value != 0.0 || data.axis_pair.is_some()
+            })
+            .map(|(action, data)| RecordedAction {
+                action: *action,
+                state: data.state,
+                value: data.value,
+                axis_pair: data.axis_pair.map(|axis_pair| axis_pair.xy()),
+                held_for: data.timing.current_duration,
+            })
+            .collect();
+        actions.sort_by_key(|recorded| recorded.action);
+
+        Self { player_id, actions }
+    }
+
+    // Overwrites the action state with the recorded one, whatever the devices are doing
+    pub fn apply(&self, action_state: &mut ActionState<PlayerAction>) {
+        let known_actions: Vec<PlayerAction> =
+            action_state.all_action_data().keys().copied().collect();
+        for action in known_actions {
+            *action_state.action_data_mut_or_default(&action) = ActionData::default();
+        }
+
+        for recorded in &self.actions {
+            let data = action_state.action_data_mut_or_default(&recorded.action);
+            data.state = recorded.state;
+            data.value = recorded.value;
+            data.axis_pair = recorded.axis_pair.map(DualAxisData::from_xy);
+            data.timing.current_duration = recorded.held_for;
+        }
+    }
+}
+
+// The match that is being recorded right now
+#[derive(Resource, Debug)]
+pub struct ReplayRecording {
+    replay: Replay,
+}
+
+impl ReplayRecording {
+    // Everything recorded up to the last tick
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+}
+
+// A replay that is being played back, with the viewer state
+#[derive(Resource, Debug)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub next_tick: usize,
+    pub speed_index: usize,
+    // Tick a rewind is catching up to, the simulation runs ahead at `SEEK_SPEED` until it gets there
+    pub seek_to: Option<usize>,
+}
+
+impl ReplayPlayback {
+    fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_tick: 0,
+            speed_index: NORMAL_SPEED_INDEX,
+            seek_to: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_tick >= self.replay.ticks.len()
+    }
+
+    pub fn speed(&self) -> f32 {
+        PLAYBACK_SPEEDS[self.speed_index]
+    }
+}
+
+fn replay_path_from_args() -> Option<PathBuf> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay");
+    args.next()?;
+    args.next().map(PathBuf::from)
+}
+
+fn start_recording(
+    mut commands: Commands,
+    seed: Res<MatchSeed>,
+    current_map: Res<CurrentMap>,
+    config: Res<MatchConfig>,
+    tuning: Res<Tuning>,
+) {
+    commands.insert_resource(ReplayRecording {
+        replay: Replay {
+            seed: seed.0,
+            map: current_map.0.clone(),
+            config: *config,
+            tuning: tuning.clone(),
+            ..default()
+        },
+    });
+}
+
+fn record_inputs(
+    mut recording: ResMut<ReplayRecording>,
+    players: Query<(&Player, &ActionState<PlayerAction>)>,
+) {
+    let replay = &mut recording.replay;
+    let tick = replay.ticks.len();
+
+    for (player, _) in players.iter() {
+        let already_recorded = replay.players.iter().any(|recorded| {
+            recorded.player_id == player.player_id && recorded.input_source == player.input_source
+        });
+        if !already_recorded {
+            replay.players.push(ReplayPlayer {
+                player_id: player.player_id,
+                input_source: player.input_source,
+                joined_at: tick,
+            });
+        }
+    }
+
+    let mut inputs: Vec<PlayerInput> = players
+        .iter()
+        .map(|(player, action_state)| PlayerInput::capture(player.player_id, action_state))
+        .collect();
+    inputs.sort_by_key(|input| input.player_id);
+    replay.ticks.push(ReplayTick { inputs });
+}
+
+fn save_recording(mut commands: Commands, recording: Option<Res<ReplayRecording>>) {
+    let Some(recording) = recording else {
+        return;
+    };
+    commands.remove_resource::<ReplayRecording>();
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = Path::new(REPLAY_DIRECTORY).join(format!("{timestamp}.replay.ron"));
+    match recording.replay.save(&path) {
+        Ok(()) => info!("Replay saved to {}", path.display()),
+        Err(error) => error!("{error}"),
+    }
+}
+
+// The players that were there from the start join before the match does, so
+// it sets them up the same way it did when it was recorded
+fn start_playback(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    mut joined_players: ResMut<JoinedPlayers>,
+) {
+    // Leaving a finished replay for the menu doesn't start it over
+    if playback.next_tick == 0 {
+        join_recorded_players(&mut commands, &playback, &mut joined_players, 0);
+        commands.insert_resource(NextState::Pending(AppState::InGame));
+    }
+}
+
+// Spawns the players that joined during the match on the tick they joined
+fn spawn_replay_players(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    mut joined_players: ResMut<JoinedPlayers>,
+) {
+    if playback.next_tick > 0 {
+        join_recorded_players(
+            &mut commands,
+            &playback,
+            &mut joined_players,
+            playback.next_tick,
+        );
+    }
+}
+
+// The recorded players get an empty input map, so only the replay moves them
+fn join_recorded_players(
+    commands: &mut Commands,
+    playback: &ReplayPlayback,
+    joined_players: &mut JoinedPlayers,
+    tick: usize,
+) {
+    for recorded in playback
+        .replay
+        .players
+        .iter()
+        .filter(|recorded| recorded.joined_at == tick)
+    {
+        let player = spawn_player(
+            commands,
+            InputMap::default(),
+            recorded.input_source,
+            recorded.player_id,
+        );
+        joined_players.0.insert(recorded.input_source, player);
+    }
+}
+
+fn play_back_inputs(
+    mut playback: ResMut<ReplayPlayback>,
+    mut players: Query<(&Player, &mut ActionState<PlayerAction>, &mut InputBuffer)>,
+    mut time: ResMut<Time<Virtual>>,
+    tick: Res<FixedTick>,
+) {
+    let Some(replay_tick) = playback.replay.ticks.get(playback.next_tick) else {
+        if !time.is_paused() {
+            info!("Replay finished");
+            time.pause();
+        }
+        return;
+    };
+
+    for (player, mut action_state, mut input_buffer) in players.iter_mut() {
+        match replay_tick
+            .inputs
+            .iter()
+            .find(|input| input.player_id == player.player_id)
+        {
+            Some(input) => input.apply(&mut action_state),
+            None => PlayerInput::default().apply(&mut action_state),
+        }
+        buffer_just_pressed(&action_state, &mut input_buffer, &tick);
+    }
+    playback.next_tick += 1;
+
+    if playback
+        .seek_to
+        .is_some_and(|seek_to| playback.next_tick >= seek_to)
+    {
+        playback.seek_to = None;
+    }
+}
+
+// , jumps back a few seconds. There is no way to undo a tick, so the match is
+// started over and played again from the recording up to that point
+fn seek_backwards(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    players: Query<Entity, With<Player>>,
+    mut joined_players: ResMut<JoinedPlayers>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Comma) {
+        return;
+    }
+
+    // Pressing again while still catching up jumps back from where the last rewind was going
+    let seek_to = playback
+        .seek_to
+        .unwrap_or(playback.next_tick)
+        .saturating_sub(SEEK_BACK_TICKS);
+    info!("Rewinding the replay to tick {seek_to}");
+
+    // The recorded players join again on the ticks they joined on
+    for player in players.iter() {
+        commands.entity(player).despawn_recursive();
+    }
+    joined_players.0.clear();
+
+    playback.next_tick = 0;
+    playback.seek_to = Some(seek_to);
+    commands.insert_resource(NextState::Pending(AppState::Menu));
+    time.unpause();
+}
+
+// P pauses, - and = change the speed, holding . fast forwards and , rewinds
+fn control_playback(
+    mut playback: ResMut<ReplayPlayback>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyP) && !playback.is_finished() {
+        if time.is_paused() {
+            time.unpause();
+        } else {
+            time.pause();
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Minus) {
+        playback.speed_index = playback.speed_index.saturating_sub(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::Equal) {
+        playback.speed_index = (playback.speed_index + 1).min(PLAYBACK_SPEEDS.len() - 1);
+    }
+
+    let speed = if playback.seek_to.is_some() {
+        SEEK_SPEED
+    } else if keyboard_input.pressed(KeyCode::Period) {
+        SCRUB_SPEED
+    } else {
+        playback.speed()
+    };
+    time.set_relative_speed(speed);
+}
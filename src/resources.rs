@@ -1,17 +1,53 @@
 use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+// The device a player is controlled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputSource {
+    Gamepad(Gamepad),
+    KeyboardMouse,
+    Keyboard(KeyboardLayout),
+    // A computer controlled player, numbered so several can join
+    Bot(usize),
+    // A player driven through the gym API
+    Agent(usize),
+}
+
+// Which half of a shared keyboard a player uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyboardLayout {
+    // WASD to move, Q and E for actions
+    Left,
+    // Arrows to move, right shift and control for actions
+    Right,
+}
 
 // This is used to check if a player already exists and which entity to disconnect
 #[derive(Resource, Default)]
-pub struct JoinedPlayers(pub HashMap<Gamepad, Entity>);
+pub struct JoinedPlayers(pub HashMap<InputSource, Entity>);
 
-#[derive(Resource)]
-pub struct Player2Lives {
-    pub lives: usize,
+// Seed for the randomness of the current match, kept so the match can be replayed
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct MatchSeed(pub u64);
+
+// The Tiled map the arena is built from
+#[derive(Resource, Debug, Clone)]
+pub struct CurrentMap(pub String);
+
+impl Default for CurrentMap {
+    fn default() -> Self {
+        Self("map.tmx".to_string())
+    }
 }
 
-#[derive(Resource)]
-pub struct Player1Lives {
-    pub lives: usize,
+// Open while a player has the match paused, the match waits until it is closed
+#[derive(Resource, Debug)]
+pub struct PauseMenu {
+    pub player: Entity,
+    pub player_id: usize,
+    pub selected: usize,
+    // The stick has to go back to the middle before it moves the selection again
+    pub stick_tilted: bool,
 }
 
 #[derive(Resource)]
@@ -20,10 +56,13 @@ pub struct SpawnTimer(pub Timer);
 #[derive(Resource)]
 pub struct DifficultyTimer(pub Timer);
 
+// Steps of the countdown, "3..", "2..", "1.." and "GO.."
+const COUNTDOWN_STEPS: u8 = 4;
+
 #[derive(Resource)]
 pub struct CountdownTimer {
-    pub(crate) timer: Timer,
-    pub(crate) duration: u8,
+    pub timer: Timer,
+    pub duration: u8,
 }
 
 impl CountdownTimer {
@@ -33,6 +72,17 @@ impl CountdownTimer {
             timer: Timer::from_seconds(1.0, TimerMode::Repeating),
         }
     }
+
+    // Runs the whole countdown from the start, play resumes once it is over
+    pub fn start(&mut self) {
+        self.duration = COUNTDOWN_STEPS;
+        self.timer.reset();
+    }
+
+    // Keeps the match paused, a countdown that is already running doesn't resume it
+    pub fn stop(&mut self) {
+        self.duration = 0;
+    }
 }
 
 impl Default for CountdownTimer {
@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+
+use crate::{
+    ball::{spawn_loose_ball, PossessionCause, PossessionChanged},
+    direction_indicator::DirectionIndicator,
+    match_setup::MatchConfig,
+    player::{collision_with_enemy, Appearance, Player, PlayerDirection},
+    rounds::RoundMode,
+    simulation::GameplaySet,
+    tuning::Tuning,
+};
+
+const DOWNED_COLOR: Color = Color::srgba(0.5, 0.5, 0.5, 0.6);
+// How far in front of a downed carrier the ball lands. Any closer and they would
+// catch it straight back, which revives them
+const DROP_OFFSET: f32 = 32.0;
+
+pub struct RevivePlugin;
+
+impl Plugin for RevivePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                drop_ball_when_downed
+                    .after(collision_with_enemy)
+                    .in_set(GameplaySet::Collision),
+                (
+                    bleed_out,
+                    (revive_nearby, revive_by_pass).run_if(players_are_teammates),
+                )
+                    .chain()
+                    .in_set(GameplaySet::Rules),
+            ),
+        );
+    }
+}
+
+// A player that ran out of lives. Teammates can bring them back until the
+// bleed-out timer runs out, after that they sit out the rest of the match
+#[derive(Component, Debug)]
+pub struct Downed {
+    bleed_out: Timer,
+    revive_progress: f32,
+}
+
+impl Downed {
+    pub fn new(bleed_out: f32) -> Self {
+        Self {
+            bleed_out: Timer::from_seconds(bleed_out, TimerMode::Once),
+            revive_progress: 0.0,
+        }
+    }
+
+    pub fn has_bled_out(&self) -> bool {
+        self.bleed_out.finished()
+    }
+}
+
+pub fn down_player(commands: &mut Commands, player_entity: Entity, bleed_out: f32) {
+    commands
+        .entity(player_entity)
+        .insert(Downed::new(bleed_out));
+}
+
+// Nobody carries the ball while down, it is dropped where they went down
+fn drop_ball_when_downed(
+    mut commands: Commands,
+    mut downed_players: Query<(Entity, &Transform, &PlayerDirection, &mut Player), Added<Downed>>,
+    indicators: Query<(Entity, &Parent), With<DirectionIndicator>>,
+    mut possession_changed: EventWriter<PossessionChanged>,
+) {
+    for (entity, transform, player_direction, mut player) in downed_players.iter_mut() {
+        if !player.have_ball {
+            continue;
+        }
+
+        player.have_ball = false;
+        for (indicator, _) in indicators
+            .iter()
+            .filter(|(_, parent)| parent.get() == entity)
+        {
+            commands.entity(indicator).despawn_recursive();
+        }
+        let facing = player_direction
+            .direction
+            .try_normalize()
+            .unwrap_or(Vec2::Y);
+        spawn_loose_ball(
+            &mut commands,
+            transform.translation.truncate() + facing * DROP_OFFSET,
+        );
+        possession_changed.send(PossessionChanged {
+            from: Some(entity),
+            to: None,
+            cause: PossessionCause::Drop,
+        });
+        info!("Player {} dropped the ball", player.player_id + 1);
+    }
+}
+
+fn revive(commands: &mut Commands, player_entity: Entity, player: &mut Player, lives: u32) {
+    player.lives = lives;
+    commands.entity(player_entity).remove::<Downed>();
+    info!("Player {} has been revived", player.player_id + 1);
+}
+
+fn bleed_out(mut downed_players: Query<(&Player, &mut Downed)>, time: Res<Time>) {
+    for (player, mut downed) in downed_players.iter_mut() {
+        if downed.has_bled_out() {
+            continue;
+        }
+
+        downed.bleed_out.tick(time.delta());
+        if downed.has_bled_out() {
+            info!("Player {} has bled out", player.player_id + 1);
+        }
+    }
+}
+
+// In free for all everyone else is a rival, nobody brings anyone back
+fn players_are_teammates(config: Res<MatchConfig>) -> bool {
+    config.rules.mode == RoundMode::CoOp
+}
+
+fn revive_nearby(
+    mut commands: Commands,
+    mut downed_players: Query<(Entity, &Transform, &mut Player, &mut Downed)>,
+    rescuers: Query<&Transform, (With<Player>, Without<Downed>)>,
+    tuning: Res<Tuning>,
+    time: Res<Time>,
+) {
+    let revive_tuning = tuning.revive;
+
+    for (entity, transform, mut player, mut downed) in downed_players.iter_mut() {
+        if downed.has_bled_out() {
+            continue;
+        }
+
+        let rescuer_nearby = rescuers.iter().any(|rescuer| {
+            rescuer.translation.distance(transform.translation) <= revive_tuning.radius
+        });
+
+        // Progress only builds up while a teammate stays close
+        if !rescuer_nearby {
+            downed.revive_progress = 0.0;
+            continue;
+        }
+
+        downed.revive_progress += time.delta_seconds();
+        if downed.revive_progress >= revive_tuning.revive_time {
+            revive(
+                &mut commands,
+                entity,
+                &mut player,
+                revive_tuning.revived_lives,
+            );
+        }
+    }
+}
+
+fn revive_by_pass(
+    mut commands: Commands,
+    mut possession_changed: EventReader<PossessionChanged>,
+    mut downed_players: Query<(&mut Player, &Downed)>,
+    tuning: Res<Tuning>,
+) {
+    for event in possession_changed.read() {
+        let (PossessionCause::Catch, Some(receiver)) = (event.cause, event.to) else {
+            continue;
+        };
+        let Ok((mut player, downed)) = downed_players.get_mut(receiver) else {
+            continue;
+        };
+        if downed.has_bled_out() {
+            continue;
+        }
+
+        revive(
+            &mut commands,
+            receiver,
+            &mut player,
+            tuning.revive.revived_lives,
+        );
+    }
+}
+
+// Grays out downed players until they are revived
+pub fn tint_downed_players(
+    mut players: Query<(&mut Sprite, &Appearance, Has<Downed>), With<Player>>,
+) {
+    for (mut sprite, appearance, downed) in players.iter_mut() {
+        sprite.color = if downed {
+            DOWNED_COLOR
+        } else {
+            appearance.color()
+        };
+    }
+}
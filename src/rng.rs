@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::resources::MatchSeed;
+
+// Environment variable the seed can be set with, `--seed` takes precedence over it
+pub const SEED_VARIABLE: &str = "BIRDSTOMP_SEED";
+
+pub struct RngPlugin {
+    // Seed from the settings, see `BirdstompSettings::seed`
+    pub seed: Option<u64>,
+}
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        // `--seed <number>` makes a match repeatable, otherwise every run is different
+        let seed = seed_from_args().or(self.seed).unwrap_or_else(rand::random);
+
+        app.insert_resource(MatchSeed(seed))
+            .add_systems(PreStartup, seed_game_rng);
+    }
+}
+
+// All gameplay randomness comes from here, never from `thread_rng`.
+// Each stream is seeded separately, so drawing more numbers for one kind of
+// thing (like a new cosmetic effect) doesn't shift the others
+#[derive(Resource)]
+pub struct GameRng {
+    // Where and when things appear in the arena
+    pub spawns: StdRng,
+    // Outcomes decided by the rules, like fumbles
+    pub gameplay: StdRng,
+    // Decisions made by computer controlled players
+    pub ai: StdRng,
+    // Effects that don't change the outcome of a match
+    pub cosmetic: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        let stream = |index: u64| StdRng::seed_from_u64(seed.wrapping_add(index));
+
+        Self {
+            spawns: stream(0),
+            gameplay: stream(1),
+            ai: stream(2),
+            cosmetic: stream(3),
+        }
+    }
+}
+
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--seed");
+    args.next()?;
+    parse_seed(&args.next()?)
+}
+
+pub fn seed_from_env() -> Option<u64> {
+    parse_seed(&std::env::var(SEED_VARIABLE).ok()?)
+}
+
+fn parse_seed(seed: &str) -> Option<u64> {
+    match seed.parse() {
+        Ok(seed) => Some(seed),
+        Err(error) => {
+            warn!("Invalid seed {seed}: {error}, ignoring it");
+            None
+        }
+    }
+}
+
+// Reseeded from the same seed whenever a match starts, see `MatchPlugin`,
+// so it plays out the same given the same input
+fn seed_game_rng(mut commands: Commands, seed: Res<MatchSeed>) {
+    commands.insert_resource(GameRng::from_seed(seed.0));
+}
@@ -0,0 +1,346 @@
+use std::{collections::BTreeMap, fmt};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ball::{spawn_loose_ball, Ball},
+    enemy::{enemy_spawn_translation, Enemy},
+    gamepad::AwaitingReconnect,
+    lobby::{ChangeLobbySetting, LobbySet, LobbySetting, SettingStep},
+    match_setup::MatchConfig,
+    player::{Player, PlayerReset},
+    resources::{CountdownTimer, PauseMenu},
+    revive::Downed,
+    rng::GameRng,
+    simulation::{GameplaySet, InterpolatedTranslation},
+    tuning::Tuning,
+    AppState, GameState,
+};
+
+// How long the scoreboard shows between two rounds
+const INTERMISSION_SECONDS: f32 = 3.0;
+// Choices the lobby cycles through
+const ROUNDS_TO_WIN_CHOICES: [u32; 4] = [1, 2, 3, 5];
+const ROUND_SECONDS_CHOICES: [Option<f32>; 4] = [None, Some(60.0), Some(90.0), Some(120.0)];
+
+pub struct RoundsPlugin;
+
+impl Plugin for RoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ResetRound>()
+            .init_resource::<MatchScore>()
+            .init_resource::<RoundClock>()
+            .add_systems(
+                Update,
+                configure_rules
+                    .after(LobbySet)
+                    .run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(FixedUpdate, end_round.in_set(GameplaySet::Round))
+            // The intermission runs while the match is paused, like the countdown
+            .add_systems(
+                FixedUpdate,
+                (
+                    start_next_round.run_if(not(resource_exists::<PauseMenu>)),
+                    reset_round,
+                )
+                    .chain()
+                    .before(GameplaySet::Input)
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+// Starts the current round over, with the countdown
+#[derive(Event, Debug)]
+pub struct ResetRound;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundMode {
+    // Everyone against the enemy, the round is lost once every player is down
+    // and won by holding out until the time runs out
+    #[default]
+    CoOp,
+    // Every player for themselves, the last one standing takes the round
+    FreeForAll,
+}
+
+impl RoundMode {
+    pub fn next(self) -> Self {
+        match self {
+            RoundMode::CoOp => RoundMode::FreeForAll,
+            RoundMode::FreeForAll => RoundMode::CoOp,
+        }
+    }
+}
+
+// Picked in the lobby, part of the `MatchConfig`. The default is a single round played until everyone is down
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MatchRules {
+    pub mode: RoundMode,
+    pub rounds_to_win: u32,
+    // `None` plays every round until someone is eliminated
+    pub round_seconds: Option<f32>,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            mode: RoundMode::CoOp,
+            rounds_to_win: 1,
+            round_seconds: None,
+        }
+    }
+}
+
+// Whoever can take a round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Contender {
+    // All players together, in co-op
+    Players,
+    // One player by id, in free for all
+    Player(usize),
+    Enemy,
+}
+
+impl fmt::Display for Contender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Contender::Players => write!(f, "The players"),
+            Contender::Player(player_id) => write!(f, "Player {}", player_id + 1),
+            Contender::Enemy => write!(f, "The enemy"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundOutcome {
+    Won(Contender),
+    Draw,
+}
+
+impl fmt::Display for RoundOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoundOutcome::Won(contender) => write!(f, "{contender} took it"),
+            RoundOutcome::Draw => write!(f, "it's a draw"),
+        }
+    }
+}
+
+// Rounds of the current match
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MatchScore {
+    // The round being played or about to start, counted from 1
+    pub round: u32,
+    pub results: Vec<RoundOutcome>,
+    pub wins: BTreeMap<Contender, u32>,
+    pub winner: Option<Contender>,
+}
+
+impl MatchScore {
+    pub fn wins(&self, contender: Contender) -> u32 {
+        self.wins.get(&contender).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct RoundClock {
+    // Counts down while a round with a time limit is played
+    pub time_left: Option<Timer>,
+    // Runs while the scoreboard shows between two rounds
+    pub intermission: Option<Timer>,
+}
+
+impl RoundClock {
+    pub fn for_round(rules: &MatchRules) -> Self {
+        Self {
+            time_left: rules
+                .round_seconds
+                .map(|seconds| Timer::from_seconds(seconds, TimerMode::Once)),
+            intermission: None,
+        }
+    }
+
+    pub fn in_intermission(&self) -> bool {
+        self.intermission.is_some()
+    }
+}
+
+// Applies the rules changed in the lobby
+fn configure_rules(
+    mut setting_changes: EventReader<ChangeLobbySetting>,
+    mut config: ResMut<MatchConfig>,
+) {
+    for change in setting_changes.read() {
+        match change.setting {
+            LobbySetting::RoundsToWin => {
+                config.rules.rounds_to_win = step_choice(
+                    &ROUNDS_TO_WIN_CHOICES,
+                    config.rules.rounds_to_win,
+                    change.step,
+                );
+                info!("First to {} rounds wins", config.rules.rounds_to_win);
+            }
+            LobbySetting::RoundTime => {
+                config.rules.round_seconds = step_choice(
+                    &ROUND_SECONDS_CHOICES,
+                    config.rules.round_seconds,
+                    change.step,
+                );
+                match config.rules.round_seconds {
+                    Some(seconds) => info!("Rounds last {seconds} seconds"),
+                    None => info!("Rounds have no time limit"),
+                }
+            }
+            // With two modes, stepping either way switches to the other one
+            LobbySetting::Mode => {
+                config.rules.mode = config.rules.mode.next();
+                info!("Mode set to {:?}", config.rules.mode);
+            }
+            _ => {}
+        }
+    }
+}
+
+// The choice before or after `current`, wrapping around. Unknown values start over at the first choice
+fn step_choice<T: Copy + PartialEq>(choices: &[T], current: T, step: SettingStep) -> T {
+    let len = choices.len();
+    let index = choices.iter().position(|choice| *choice == current);
+    choices[match (index, step) {
+        (None, _) => 0,
+        (Some(index), SettingStep::Previous) => (index + len - 1) % len,
+        (Some(index), SettingStep::Next) => (index + 1) % len,
+    }]
+}
+
+fn end_round(
+    mut commands: Commands,
+    players: Query<(&Player, Has<Downed>)>,
+    config: Res<MatchConfig>,
+    mut score: ResMut<MatchScore>,
+    mut clock: ResMut<RoundClock>,
+    mut countdown: ResMut<CountdownTimer>,
+    time: Res<Time>,
+) {
+    if players.is_empty() {
+        return;
+    }
+
+    let time_up = clock
+        .time_left
+        .as_mut()
+        .is_some_and(|timer| timer.tick(time.delta()).finished());
+    let standing: Vec<&Player> = players
+        .iter()
+        .filter(|(_, downed)| !downed)
+        .map(|(player, _)| player)
+        .collect();
+
+    let outcome = match (config.rules.mode, standing.as_slice()) {
+        (_, []) => RoundOutcome::Won(Contender::Enemy),
+        (RoundMode::FreeForAll, [last]) if players.iter().count() > 1 => {
+            RoundOutcome::Won(Contender::Player(last.player_id))
+        }
+        (RoundMode::CoOp, _) if time_up => RoundOutcome::Won(Contender::Players),
+        (RoundMode::FreeForAll, _) if time_up => leader(&standing),
+        _ => return,
+    };
+
+    info!("Round {} is over, {}", score.round, outcome);
+    score.results.push(outcome);
+    commands.insert_resource(NextState::Pending(GameState::Paused));
+    // A hit on the last tick of the round may have started the countdown
+    countdown.stop();
+
+    if let RoundOutcome::Won(contender) = outcome {
+        let wins = score.wins.entry(contender).or_default();
+        *wins += 1;
+        if *wins >= config.rules.rounds_to_win {
+            info!("{contender} won the match");
+            score.winner = Some(contender);
+            commands.insert_resource(NextState::Pending(AppState::GameOver));
+            return;
+        }
+    }
+
+    clock.time_left = None;
+    clock.intermission = Some(Timer::from_seconds(INTERMISSION_SECONDS, TimerMode::Once));
+}
+
+// The player with the most lives left, then the highest score. A tie is a draw
+fn leader(standing: &[&Player]) -> RoundOutcome {
+    let best = |player: &Player| (player.lives, player.score);
+    let Some(leader) = standing.iter().max_by_key(|player| best(player)) else {
+        return RoundOutcome::Draw;
+    };
+    if standing
+        .iter()
+        .filter(|player| best(player) == best(leader))
+        .count()
+        > 1
+    {
+        return RoundOutcome::Draw;
+    }
+    RoundOutcome::Won(Contender::Player(leader.player_id))
+}
+
+// Moves on to the next round once the scoreboard has been shown
+fn start_next_round(
+    mut clock: ResMut<RoundClock>,
+    mut score: ResMut<MatchScore>,
+    awaiting_reconnect: Query<(), With<AwaitingReconnect>>,
+    mut reset_round: EventWriter<ResetRound>,
+    time: Res<Time>,
+) {
+    // Wait for missing controllers before going on
+    if !awaiting_reconnect.is_empty() {
+        return;
+    }
+    let Some(intermission) = clock.intermission.as_mut() else {
+        return;
+    };
+    if !intermission.tick(time.delta()).finished() {
+        return;
+    }
+
+    clock.intermission = None;
+    score.round += 1;
+    reset_round.send(ResetRound);
+}
+
+// Puts everyone back where a match starts and runs the countdown into the round
+fn reset_round(
+    mut commands: Commands,
+    mut reset_round: EventReader<ResetRound>,
+    mut clock: ResMut<RoundClock>,
+    score: Res<MatchScore>,
+    config: Res<MatchConfig>,
+    mut countdown: ResMut<CountdownTimer>,
+    mut player_reset: PlayerReset,
+    mut enemies: Query<(&mut Transform, &mut InterpolatedTranslation, &mut Enemy), Without<Player>>,
+    balls: Query<Entity, With<Ball>>,
+    mut rng: ResMut<GameRng>,
+    tuning: Res<Tuning>,
+) {
+    if reset_round.read().count() == 0 {
+        return;
+    }
+
+    player_reset.reset_all(config.starting_lives);
+    for entity in balls.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_loose_ball(&mut commands, Vec2::ZERO);
+
+    for (mut transform, mut interpolated, mut enemy) in enemies.iter_mut() {
+        transform.translation = enemy_spawn_translation(&mut rng);
+        *interpolated = InterpolatedTranslation::new(transform.translation);
+        enemy.reset_speed(&tuning);
+    }
+
+    *clock = RoundClock::for_round(&config.rules);
+    countdown.start();
+    info!("Round {} is about to start", score.round);
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    enemy::Enemy,
+    gamepad::PlayerAction,
+    player::{collision_with_enemy, Player},
+    revive::Downed,
+    simulation::GameplaySet,
+    tuning::Tuning,
+};
+
+pub struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShieldBlocked>().add_systems(
+            FixedUpdate,
+            (
+                raise_shield.in_set(GameplaySet::Input),
+                knock_back_blocked_enemy
+                    .after(collision_with_enemy)
+                    .in_set(GameplaySet::Collision),
+            ),
+        );
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Shield {
+    raised: bool,
+    window: Timer,
+    cooldown: Timer,
+}
+
+impl Shield {
+    pub fn is_raised(&self) -> bool {
+        self.raised
+    }
+
+    // Drops the shield and starts the cooldown before it can be raised again
+    pub fn lower(&mut self, cooldown: f32) {
+        self.raised = false;
+        self.cooldown = Timer::from_seconds(cooldown, TimerMode::Once);
+    }
+}
+
+impl Default for Shield {
+    fn default() -> Self {
+        Self {
+            raised: false,
+            window: Timer::from_seconds(0.0, TimerMode::Once),
+            cooldown: Timer::from_seconds(0.0, TimerMode::Once),
+        }
+    }
+}
+
+// Marks the child sprite that is shown while the shield is up
+#[derive(Component)]
+pub struct ShieldSprite;
+
+// Sent when a raised shield absorbs a hit from the enemy
+#[derive(Event, Debug)]
+pub struct ShieldBlocked {
+    pub player: Entity,
+    pub enemy: Entity,
+}
+
+pub fn spawn_shield_sprite(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    texture_atlases_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
+) {
+    // The sheet holds a single frame
+    let layout = TextureAtlasLayout::from_grid(UVec2::new(64, 64), 1, 1, None, None);
+
+    parent.spawn((
+        SpriteSheetBundle {
+            texture: asset_server.load("Ducky/Spritesheets/shield.png"),
+            atlas: TextureAtlas {
+                layout: texture_atlases_layouts.add(layout),
+                index: 0,
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ShieldSprite,
+    ));
+}
+
+fn raise_shield(
+    mut players: Query<(&ActionState<PlayerAction>, &mut Shield), Without<Downed>>,
+    tuning: Res<Tuning>,
+    time: Res<Time>,
+) {
+    let shield_tuning = tuning.shield;
+
+    for (action_state, mut shield) in players.iter_mut() {
+        shield.cooldown.tick(time.delta());
+
+        if shield.raised {
+            shield.window.tick(time.delta());
+            if shield.window.finished() || !action_state.pressed(&PlayerAction::Shield) {
+                shield.lower(shield_tuning.cooldown);
+            }
+        } else if action_state.just_pressed(&PlayerAction::Shield) && shield.cooldown.finished() {
+            shield.raised = true;
+            shield.window = Timer::from_seconds(shield_tuning.block_window, TimerMode::Once);
+        }
+    }
+}
+
+fn knock_back_blocked_enemy(
+    mut events: EventReader<ShieldBlocked>,
+    mut players: Query<(&Transform, &mut Player), Without<Enemy>>,
+    mut enemies: Query<&mut Transform, With<Enemy>>,
+    tuning: Res<Tuning>,
+) {
+    for event in events.read() {
+        let Ok((player_transform, mut player)) = players.get_mut(event.player) else {
+            continue;
+        };
+        let Ok(mut enemy_transform) = enemies.get_mut(event.enemy) else {
+            continue;
+        };
+
+        let push_direction = (enemy_transform.translation - player_transform.translation)
+            .truncate()
+            .normalize_or_zero();
+        enemy_transform.translation += (push_direction * tuning.shield.knockback).extend(0.0);
+
+        player.score += tuning.shield.block_bonus;
+        info!(
+            "Player {} blocked the enemy, score: {}",
+            player.player_id, player.score
+        );
+    }
+}
+
+pub fn show_shield(
+    players: Query<&Shield>,
+    mut shield_sprites: Query<(&Parent, &mut Visibility), With<ShieldSprite>>,
+) {
+    for (parent, mut visibility) in shield_sprites.iter_mut() {
+        let Ok(shield) = players.get(parent.get()) else {
+            continue;
+        };
+
+        *visibility = if shield.is_raised() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    resources::{CountdownTimer, CurrentMap},
+    AppState, GameState,
+};
+
+// Length of one simulation tick, shared by gameplay and physics
+pub const FIXED_TIMESTEP: f32 = 1.0 / 64.0;
+
+// Gameplay runs in `FixedUpdate` in this order, before the physics step.
+// Everything in these sets only runs while a match is being played
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameplaySet {
+    // Turning held buttons into shields, grabs and aiming
+    Input,
+    // Moving players and the enemy
+    Movement,
+    // Catching and throwing the ball
+    Ball,
+    // Grabs taking the ball from its carrier
+    Steal,
+    // Resolving contacts with the enemy
+    Collision,
+    // Scores and revives
+    Rules,
+    // Ending the round once everything else in the tick is settled
+    Round,
+}
+
+// Sent every second of the countdown with how many steps were left, the match resumes at 1
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CountdownAdvanced(pub u8);
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CountdownTimer>()
+            .init_resource::<CurrentMap>()
+            .add_event::<CountdownAdvanced>()
+            .insert_resource(Time::<Fixed>::from_seconds(FIXED_TIMESTEP as f64))
+            .insert_resource(TimestepMode::Fixed {
+                dt: FIXED_TIMESTEP,
+                substeps: 1,
+            })
+            .configure_sets(
+                FixedUpdate,
+                (
+                    GameplaySet::Input,
+                    GameplaySet::Movement,
+                    GameplaySet::Ball,
+                    GameplaySet::Steal,
+                    GameplaySet::Collision,
+                    GameplaySet::Rules,
+                    GameplaySet::Round,
+                )
+                    .chain()
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Running)),
+            )
+            // The countdown decides when play resumes, so it counts fixed ticks too
+            .add_systems(
+                FixedUpdate,
+                tick_countdown
+                    .before(GameplaySet::Input)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(FixedFirst, restore_simulated_translation)
+            .add_systems(FixedPostUpdate, record_simulated_translation);
+    }
+}
+
+// Entities moved by the simulation are drawn in between their last two
+// simulated positions, so movement looks smooth at any frame rate
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InterpolatedTranslation {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl InterpolatedTranslation {
+    pub fn new(translation: Vec3) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+        }
+    }
+}
+
+fn tick_countdown(
+    mut commands: Commands,
+    mut countdown: ResMut<CountdownTimer>,
+    mut countdown_advanced: EventWriter<CountdownAdvanced>,
+    time: Res<Time>,
+) {
+    countdown.timer.tick(time.delta());
+    if !countdown.timer.just_finished() {
+        return;
+    }
+
+    countdown_advanced.send(CountdownAdvanced(countdown.duration));
+    if countdown.duration == 1 {
+        commands.insert_resource(NextState::Pending(GameState::Running));
+    }
+    if countdown.duration != 0 {
+        countdown.duration -= 1;
+    }
+}
+
+// Puts back the simulated position before the next tick, the drawn one is only for show
+fn restore_simulated_translation(mut query: Query<(&mut Transform, &InterpolatedTranslation)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.current;
+    }
+}
+
+fn record_simulated_translation(mut query: Query<(&Transform, &mut InterpolatedTranslation)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = transform.translation;
+    }
+}
+
+// Only added with `PresentationPlugin`, headless runs keep the simulated positions
+pub fn interpolate_translation(
+    mut query: Query<(&mut Transform, &InterpolatedTranslation)>,
+    time: Res<Time<Fixed>>,
+) {
+    let alpha = time.overstep_fraction();
+
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.previous.lerp(interpolated.current, alpha);
+    }
+}
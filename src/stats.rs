@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    ball::{Ball, PossessionCause, PossessionChanged},
+    enemy::Enemy,
+    player::{Player, PlayerHit},
+    revive::Downed,
+    shield::ShieldBlocked,
+    AppState, GameState,
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchStats>()
+            // Counted after the tick, once every gameplay event of it has been sent
+            .add_systems(
+                FixedPostUpdate,
+                (count_events, count_possession_time)
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PlayerStats {
+    pub lives_lost: u32,
+    // Time spent on their feet, not downed
+    pub survival_seconds: f32,
+    pub possession_seconds: f32,
+    pub catches: u32,
+    pub throws: u32,
+    // Throws that a teammate caught
+    pub passes: u32,
+    pub steals: u32,
+    pub fumbles: u32,
+    pub blocks: u32,
+    // Thrown balls that hit the enemy. It has no health to take yet, so every hit counts one
+    pub damage_dealt: u32,
+    pub score: u32,
+    pub downed: bool,
+}
+
+// What happened in the current match, per player id
+#[derive(Resource, Serialize, Debug, Clone, Default)]
+pub struct MatchStats {
+    pub ticks_played: u64,
+    pub players: BTreeMap<usize, PlayerStats>,
+    // How fast the enemy was going every time it hit a player
+    pub enemy_speed_at_hits: Vec<f32>,
+}
+
+impl MatchStats {
+    fn player(&mut self, players: &Query<&Player>, entity: Entity) -> Option<&mut PlayerStats> {
+        let player = players.get(entity).ok()?;
+        Some(self.players.entry(player.player_id).or_default())
+    }
+}
+
+fn count_events(
+    mut stats: ResMut<MatchStats>,
+    mut possession_changed: EventReader<PossessionChanged>,
+    mut player_hit: EventReader<PlayerHit>,
+    mut shield_blocked: EventReader<ShieldBlocked>,
+    mut collisions: EventReader<CollisionEvent>,
+    players: Query<&Player>,
+    enemies: Query<&Enemy>,
+    balls: Query<(), With<Ball>>,
+    // Whoever threw the ball that is in the air right now
+    mut last_thrower: Local<Option<Entity>>,
+) {
+    for event in possession_changed.read() {
+        match (event.cause, event.from, event.to) {
+            (PossessionCause::Throw, thrower, _) => *last_thrower = thrower,
+            (PossessionCause::Catch, _, Some(receiver)) => {
+                if let Some(thrower) = last_thrower.take().filter(|thrower| *thrower != receiver) {
+                    if let Some(player_stats) = stats.player(&players, thrower) {
+                        player_stats.passes += 1;
+                    }
+                }
+            }
+            _ => *last_thrower = None,
+        }
+
+        let (player, count): (Option<Entity>, fn(&mut PlayerStats)) = match event.cause {
+            PossessionCause::Catch => (event.to, |stats| stats.catches += 1),
+            PossessionCause::Throw => (event.from, |stats| stats.throws += 1),
+            PossessionCause::Steal => (event.to, |stats| stats.steals += 1),
+            PossessionCause::Fumble => (event.from, |stats| stats.fumbles += 1),
+            PossessionCause::Drop => continue,
+        };
+        if let Some(player_stats) = player.and_then(|player| stats.player(&players, player)) {
+            count(player_stats);
+        }
+    }
+    for event in player_hit.read() {
+        if let Some(player_stats) = stats.player(&players, event.player) {
+            player_stats.lives_lost += 1;
+        }
+        if let Ok(enemy) = enemies.get_single() {
+            stats.enemy_speed_at_hits.push(enemy.current_speed);
+        }
+    }
+    for event in shield_blocked.read() {
+        if let Some(player_stats) = stats.player(&players, event.player) {
+            player_stats.blocks += 1;
+        }
+    }
+    for event in collisions.read() {
+        let CollisionEvent::Started(first, second, _) = *event else {
+            continue;
+        };
+        let ball_hit_enemy = (balls.contains(first) && enemies.contains(second))
+            || (balls.contains(second) && enemies.contains(first));
+        if !ball_hit_enemy {
+            continue;
+        }
+        let Some(thrower) = *last_thrower else {
+            continue;
+        };
+        if let Some(player_stats) = stats.player(&players, thrower) {
+            player_stats.damage_dealt += 1;
+        }
+    }
+}
+
+fn count_possession_time(
+    mut stats: ResMut<MatchStats>,
+    players: Query<(&Player, Has<Downed>)>,
+    time: Res<Time>,
+) {
+    stats.ticks_played += 1;
+
+    for (player, downed) in players.iter() {
+        let player_stats = stats.players.entry(player.player_id).or_default();
+        player_stats.score = player.score;
+        player_stats.downed = downed;
+        if !downed {
+            player_stats.survival_seconds += time.delta_seconds();
+        }
+        if player.have_ball {
+            player_stats.possession_seconds += time.delta_seconds();
+        }
+    }
+}
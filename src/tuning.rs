@@ -0,0 +1,300 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Tuning>()
+            .register_asset_loader(TuningLoader)
+            .init_resource::<Tuning>()
+            .add_systems(Startup, load_tuning)
+            .add_systems(Update, apply_loaded_tuning);
+    }
+}
+
+// The tuning asset is copied into a resource of the same type once loaded,
+// so gameplay systems keep working with the defaults while it is loading
+#[derive(Asset, Resource, TypePath, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Tuning {
+    pub movement: MovementTuning,
+    pub shield: ShieldTuning,
+    pub grab: GrabTuning,
+    pub revive: ReviveTuning,
+    pub ball: BallTuning,
+    pub enemy: EnemyTuning,
+    pub input_buffer: InputBufferTuning,
+}
+
+impl Tuning {
+    // Reads a tuning file from anywhere on disk, for tools that run outside the asset folder
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TuningLoaderError> {
+        let bytes = fs::read(path)?;
+        Self::parse(&bytes)
+    }
+
+    // Values the game can't run with are turned down here, instead of panicking mid-match
+    fn parse(bytes: &[u8]) -> Result<Self, TuningLoaderError> {
+        let tuning: Tuning = ron::de::from_bytes(bytes)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    pub fn validate(&self) -> Result<(), TuningLoaderError> {
+        let fumble_chance = self.grab.fumble_chance;
+        if !(0.0..=1.0).contains(&fumble_chance) {
+            return Err(TuningLoaderError::Invalid(format!(
+                "grab.fumble_chance is {fumble_chance}, it has to be between 0 and 1"
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Used instead of the tuning asset when present at startup, so batch runs can
+// compare tuning files without the asset reloading over them
+#[derive(Resource, Debug, Clone)]
+pub struct TuningOverride(pub Tuning);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct MovementTuning {
+    // Units per second
+    pub top_speed: f32,
+    // Units per second squared while there is movement input
+    pub acceleration: f32,
+    // Units per second squared once the input is released
+    pub deceleration: f32,
+    // Multiplier applied to the top speed while carrying the ball
+    pub ball_carry_speed_factor: f32,
+    // Speed a dash starts out at before friction slows it back down
+    pub dash_speed: f32,
+    // Seconds between dashes
+    pub dash_cooldown: f32,
+}
+
+impl Default for MovementTuning {
+    fn default() -> Self {
+        Self {
+            top_speed: 500.0,
+            acceleration: 4000.0,
+            deceleration: 3000.0,
+            ball_carry_speed_factor: 0.8,
+            dash_speed: 1400.0,
+            dash_cooldown: 0.8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ShieldTuning {
+    // Seconds the shield stays up after being raised
+    pub block_window: f32,
+    // Seconds before the shield can be raised again
+    pub cooldown: f32,
+    // Multiplier applied to the top speed while the shield is up
+    pub move_speed_factor: f32,
+    // Distance the enemy is pushed away on a block
+    pub knockback: f32,
+    // Score awarded for a successful block
+    pub block_bonus: u32,
+}
+
+impl Default for ShieldTuning {
+    fn default() -> Self {
+        Self {
+            block_window: 0.4,
+            cooldown: 2.0,
+            move_speed_factor: 0.4,
+            knockback: 150.0,
+            block_bonus: 10,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct GrabTuning {
+    // Seconds between pressing grab and reaching for the ball
+    pub wind_up: f32,
+    // Seconds before another grab can be started
+    pub cooldown: f32,
+    // Maximum distance to the carrier when the wind-up ends
+    pub range: f32,
+    // Chance from 0 to 1 that the ball pops loose instead of being stolen
+    pub fumble_chance: f64,
+    // Score awarded for a successful steal
+    pub steal_bonus: u32,
+}
+
+impl Default for GrabTuning {
+    fn default() -> Self {
+        Self {
+            wind_up: 0.25,
+            cooldown: 1.5,
+            range: 40.0,
+            fumble_chance: 0.25,
+            steal_bonus: 5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ReviveTuning {
+    // Seconds a downed player can wait for help before they are out of the match
+    pub bleed_out: f32,
+    // Distance a teammate has to stay within to revive
+    pub radius: f32,
+    // Seconds a teammate has to stay close to revive
+    pub revive_time: f32,
+    // Lives a player comes back with
+    pub revived_lives: u32,
+}
+
+impl Default for ReviveTuning {
+    fn default() -> Self {
+        Self {
+            bleed_out: 15.0,
+            radius: 60.0,
+            revive_time: 2.0,
+            revived_lives: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct BallTuning {
+    // Units per second the ball leaves the thrower with
+    pub throw_speed: f32,
+}
+
+impl Default for BallTuning {
+    fn default() -> Self {
+        Self { throw_speed: 900.0 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct EnemyTuning {
+    // Units per second the enemy starts every match with
+    pub initial_speed: f32,
+    // Units per second the enemy speeds up by for every second it chases the ball carrier
+    pub speed_ramp: f32,
+    // Distance the enemy is pushed away from a player it hit, so play doesn't resume in contact
+    pub hit_knockback: f32,
+}
+
+impl Default for EnemyTuning {
+    fn default() -> Self {
+        Self {
+            initial_speed: 400.0,
+            speed_ramp: 48.0,
+            hit_knockback: 200.0,
+        }
+    }
+}
+
+// How many fixed ticks a buffered press stays usable
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct InputBufferTuning {
+    pub throw_window: u64,
+    pub dash_window: u64,
+}
+
+impl Default for InputBufferTuning {
+    fn default() -> Self {
+        Self {
+            throw_window: 8,
+            dash_window: 6,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct TuningHandle(pub Handle<Tuning>);
+
+pub struct TuningLoader;
+
+#[derive(Debug, Error)]
+pub enum TuningLoaderError {
+    #[error("Could not load tuning file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse tuning file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Invalid tuning file: {0}")]
+    Invalid(String),
+}
+
+impl AssetLoader for TuningLoader {
+    type Asset = Tuning;
+    type Settings = ();
+    type Error = TuningLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Tuning::parse(&bytes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        static EXTENSIONS: &[&str] = &["tuning.ron"];
+        EXTENSIONS
+    }
+}
+
+fn load_tuning(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    tuning_override: Option<Res<TuningOverride>>,
+) {
+    if let Some(tuning_override) = tuning_override {
+        commands.insert_resource(tuning_override.0.clone());
+        return;
+    }
+    commands.insert_resource(TuningHandle(asset_server.load("default.tuning.ron")));
+}
+
+fn apply_loaded_tuning(
+    mut commands: Commands,
+    mut tuning_events: EventReader<AssetEvent<Tuning>>,
+    tunings: Res<Assets<Tuning>>,
+    handle: Option<Res<TuningHandle>>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    for event in tuning_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.0.id() =>
+            {
+                if let Some(tuning) = tunings.get(*id) {
+                    info!("Applying tuning: {:?}", tuning);
+                    commands.insert_resource(tuning.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
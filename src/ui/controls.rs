@@ -0,0 +1,286 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::controls::{ControlsConfig, GamepadBindings, REMAPPABLE_ACTIONS};
+use crate::feedback::RumbleIntensity;
+use crate::gamepad::PlayerAction;
+use crate::pause::OpenSettings;
+use crate::player::Player;
+use crate::ui::styles::*;
+
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.85);
+// The rumble setting is listed after the remappable actions
+const RUMBLE_ROW: usize = REMAPPABLE_ACTIONS.len();
+const RUMBLE_STEP: f32 = 0.25;
+
+#[derive(Component)]
+pub struct ControlsScreen;
+
+// An open controls screen for a single gamepad player.
+// The screen is driven by the raw gamepad buttons instead of the player's
+// bindings, so it keeps working however the buttons end up mapped
+#[derive(Resource, Debug)]
+pub struct RemapSession {
+    player: Entity,
+    player_id: usize,
+    gamepad: Gamepad,
+    gamepad_name: String,
+    bindings: GamepadBindings,
+    selected: usize,
+    waiting_for_button: bool,
+}
+
+impl RemapSession {
+    pub fn new(
+        player: Entity,
+        player_id: usize,
+        gamepad: Gamepad,
+        gamepads: &Gamepads,
+        controls_config: &ControlsConfig,
+    ) -> Self {
+        let gamepad_name = gamepads.name(gamepad).unwrap_or_default().to_string();
+        Self {
+            player,
+            player_id,
+            gamepad,
+            bindings: controls_config.bindings_for(Some(&gamepad_name)),
+            gamepad_name,
+            selected: 0,
+            waiting_for_button: false,
+        }
+    }
+}
+
+pub fn open_controls_screen(
+    mut commands: Commands,
+    players: Query<(Entity, &Player)>,
+    gamepads: Res<Gamepads>,
+    button_inputs: Res<ButtonInput<GamepadButton>>,
+    controls_config: Res<ControlsConfig>,
+) {
+    for (entity, player) in players.iter() {
+        let Some(gamepad) = player.gamepad() else {
+            continue;
+        };
+        if !button_inputs.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::North)) {
+            continue;
+        }
+
+        commands.insert_resource(RemapSession::new(
+            entity,
+            player.player_id,
+            gamepad,
+            &gamepads,
+            &controls_config,
+        ));
+        return;
+    }
+}
+
+// Settings in the pause menu opens the screen for the player who paused
+pub fn open_pause_settings(
+    mut commands: Commands,
+    mut open_settings: EventReader<OpenSettings>,
+    players: Query<&Player>,
+    gamepads: Res<Gamepads>,
+    controls_config: Res<ControlsConfig>,
+) {
+    for event in open_settings.read() {
+        let Ok(player) = players.get(event.player) else {
+            continue;
+        };
+        match player.gamepad() {
+            Some(gamepad) => commands.insert_resource(RemapSession::new(
+                event.player,
+                player.player_id,
+                gamepad,
+                &gamepads,
+                &controls_config,
+            )),
+            None => info!("Only gamepad controls can be changed"),
+        }
+    }
+}
+
+pub fn navigate_controls_screen(
+    mut commands: Commands,
+    mut session: ResMut<RemapSession>,
+    button_inputs: Res<ButtonInput<GamepadButton>>,
+    mut input_maps: Query<&mut InputMap<PlayerAction>>,
+    mut rumble_intensities: Query<&mut RumbleIntensity>,
+    mut controls_config: ResMut<ControlsConfig>,
+) {
+    let gamepad = session.gamepad;
+    let just_pressed =
+        |button_type| button_inputs.just_pressed(GamepadButton::new(gamepad, button_type));
+
+    if session.selected == RUMBLE_ROW {
+        let step = if just_pressed(GamepadButtonType::DPadLeft) {
+            -RUMBLE_STEP
+        } else if just_pressed(GamepadButtonType::DPadRight) {
+            RUMBLE_STEP
+        } else {
+            0.0
+        };
+        if step != 0.0 {
+            session.bindings.rumble = (session.bindings.rumble + step).clamp(0.0, 1.0);
+            if let Ok(mut rumble_intensity) = rumble_intensities.get_mut(session.player) {
+                rumble_intensity.0 = session.bindings.rumble;
+            }
+            return;
+        }
+    }
+    let action = REMAPPABLE_ACTIONS.get(session.selected).copied();
+
+    if session.waiting_for_button {
+        let Some(action) = action else {
+            session.waiting_for_button = false;
+            return;
+        };
+        let Some(button) = button_inputs
+            .get_just_pressed()
+            .find(|button| button.gamepad == gamepad)
+        else {
+            return;
+        };
+
+        // Swap with the action that used the button before, so no button triggers two actions
+        let previous_button = session.bindings.get(action);
+        if let Some(other_action) = REMAPPABLE_ACTIONS
+            .iter()
+            .find(|other| session.bindings.get(**other) == Some(button.button_type))
+        {
+            session.bindings.set(*other_action, previous_button);
+        }
+        session.bindings.set(action, Some(button.button_type));
+        session.waiting_for_button = false;
+
+        if let Ok(mut input_map) = input_maps.get_mut(session.player) {
+            *input_map = session.bindings.input_map(gamepad);
+        }
+        return;
+    }
+
+    if just_pressed(GamepadButtonType::DPadUp) {
+        session.selected = session.selected.checked_sub(1).unwrap_or(RUMBLE_ROW);
+    } else if just_pressed(GamepadButtonType::DPadDown) {
+        session.selected = (session.selected + 1) % (RUMBLE_ROW + 1);
+    } else if just_pressed(GamepadButtonType::South) && action.is_some() {
+        session.waiting_for_button = true;
+    } else if let (true, Some(action)) = (just_pressed(GamepadButtonType::West), action) {
+        session.bindings.set(action, None);
+        if let Ok(mut input_map) = input_maps.get_mut(session.player) {
+            *input_map = session.bindings.input_map(gamepad);
+        }
+    } else if just_pressed(GamepadButtonType::East) {
+        controls_config
+            .gamepads
+            .insert(session.gamepad_name.clone(), session.bindings.clone());
+        if let Err(error) = controls_config.save() {
+            error!("{error}");
+        }
+        commands.remove_resource::<RemapSession>();
+    }
+}
+
+pub fn refresh_controls_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    session: Option<Res<RemapSession>>,
+    screen_query: Query<Entity, With<ControlsScreen>>,
+) {
+    let session_changed = match &session {
+        Some(session) => session.is_changed(),
+        None => !screen_query.is_empty(),
+    };
+    if !session_changed {
+        return;
+    }
+
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(session) = session {
+        build_controls_screen(&mut commands, &asset_server, &session);
+    }
+}
+
+pub fn despawn_controls_screen(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<ControlsScreen>>,
+) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn close_controls_screen(mut commands: Commands) {
+    commands.remove_resource::<RemapSession>();
+}
+
+fn build_controls_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    session: &RemapSession,
+) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..FULL_CENTER_COL
+                },
+                background_color: OVERLAY_COLOR.into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            ControlsScreen,
+        ))
+        .with_children(|parent| {
+            spawn_button_text_box(
+                asset_server,
+                parent,
+                &format!("Controls P{}", session.player_id + 1),
+            );
+
+            for (index, action) in REMAPPABLE_ACTIONS.iter().enumerate() {
+                let binding = match session.bindings.get(*action) {
+                    _ if session.waiting_for_button && index == session.selected => {
+                        "press a button...".to_string()
+                    }
+                    Some(button) => format!("{:?}", button),
+                    None => "unbound".to_string(),
+                };
+                let mut text_style = get_button_text_style(asset_server);
+                if index == session.selected {
+                    text_style.color = SELECTED_COLOR;
+                }
+
+                parent.spawn(TextBundle::from_section(
+                    format!("{:?}: {}", action, binding),
+                    text_style,
+                ));
+            }
+
+            let mut text_style = get_button_text_style(asset_server);
+            if session.selected == RUMBLE_ROW {
+                text_style.color = SELECTED_COLOR;
+            }
+            let rumble = match session.bindings.rumble {
+                rumble if rumble <= 0.0 => "off".to_string(),
+                rumble => format!("{}%", (rumble * 100.0).round()),
+            };
+            parent.spawn(TextBundle::from_section(
+                format!("Rumble: < {} >", rumble),
+                text_style,
+            ));
+
+            spawn_button_text_box(
+                asset_server,
+                parent,
+                "DPad: select/adjust  A: rebind  X: unbind  B: save and back",
+            );
+        })
+        .id()
+}
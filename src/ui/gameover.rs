@@ -1,95 +1,195 @@
-// use bevy::prelude::*;
-// use crate::ui::buttons::{PlayButton, QuitButton};
-// use crate::ui::styles::{BUTTON_STYLE, CENTER_ROW, FULL_CENTER_COL, get_chicken_image_bundle, NORMAL_BUTTON_COLOR, spawn_button_text_box, spawn_end_score, spawn_title_box};
-//
-// #[derive(Component)]
-// pub struct GameOver;
-//
-// pub fn spawn_gameover(
-//     mut commands: Commands,
-//     asset_server: Res<AssetServer>, ) {
-//     build_gameover(&mut commands, &asset_server);
-// }
-//
-// pub fn despawn_gameover(
-//     mut commands: Commands,
-//     menu_query: Query<Entity, With<GameOver>>, ) {
-//     if let Ok(gameover_entity) = menu_query.get_single() {
-//         commands.entity(gameover_entity).despawn_recursive();
-//     }
-// }
-//
-// fn build_gameover(commands: &mut Commands,
-//                   asset_server: &Res<AssetServer>,
-// ) -> Entity {
-//     let gameover_entity = commands
-//         .spawn((NodeBundle {
-//             style: FULL_CENTER_COL,
-//             ..default()
-//         },
-//                 GameOver,
-//         ))
-//         .with_children(|parent| {
-//             //Final score
-//             parent.spawn(
-//                 NodeBundle {
-//                     style: CENTER_ROW,
-//                     ..default()
-//                 }
-//             ).with_children(|parent| {
-//                 spawn_end_score(asset_server, parent);
-//             });
-//         }).with_children(|parent| {
-//         //title
-//         parent.spawn(
-//             NodeBundle {
-//                 style: CENTER_ROW,
-//                 ..default()
-//             }).with_children(|parent| {
-//             //image
-//             parent.spawn(
-//                 get_chicken_image_bundle(asset_server));
-//             //Title text
-//             spawn_title_box(asset_server, parent, "Game Over");
-//             //image
-//             parent.spawn(
-//                 get_chicken_image_bundle(asset_server));
-//         }
-//         );
-//         //restartbutton
-//         parent.spawn(
-//             (
-//                 ButtonBundle {
-//                     style: BUTTON_STYLE,
-//                     background_color: NORMAL_BUTTON_COLOR.into(),
-//                     ..default()
-//                 },
-//                 PlayButton,
-//             )
-//         ).with_children(|parent| {
-//             spawn_button_text_box(asset_server, parent, "Restart")
-//         });
-//         //quitbutton
-//         parent.spawn(
-//             (
-//                 ButtonBundle {
-//                     style: BUTTON_STYLE,
-//                     background_color: NORMAL_BUTTON_COLOR.into(),
-//                     ..default()
-//                 },
-//                 QuitButton
-//             )
-//         )
-//             .with_children(|parent| {
-//                 spawn_button_text_box(asset_server, parent, "Quit")
-//             });
-//     })
-//         .id();
-//     gameover_entity
-// }
-//
-//
-//
-//
-//
-//
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use leafwing_input_manager::prelude::*;
+
+use crate::gamepad::PlayerAction;
+use crate::navigation::{read_menu_input, step_selection, MenuInput};
+use crate::player::Player;
+use crate::rounds::MatchScore;
+use crate::stats::MatchStats;
+use crate::ui::styles::*;
+use crate::AppState;
+
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.85);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GameOverOption {
+    Rematch,
+    BackToLobby,
+}
+
+const GAME_OVER_OPTIONS: [GameOverOption; 2] =
+    [GameOverOption::Rematch, GameOverOption::BackToLobby];
+
+impl GameOverOption {
+    fn label(self) -> &'static str {
+        match self {
+            GameOverOption::Rematch => "Rematch",
+            GameOverOption::BackToLobby => "Back to Lobby",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GameOverScreen;
+
+#[derive(Component)]
+pub struct GameOverOptionText(usize);
+
+// Shared by every joined player, whoever presses first decides
+#[derive(Resource, Debug, Default)]
+pub struct GameOverMenu {
+    selected: usize,
+}
+
+pub fn spawn_gameover(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stats: Res<MatchStats>,
+    score: Res<MatchScore>,
+) {
+    commands.init_resource::<GameOverMenu>();
+    build_gameover(&mut commands, &asset_server, &stats, &score);
+}
+
+pub fn despawn_gameover(mut commands: Commands, screen_query: Query<Entity, With<GameOverScreen>>) {
+    commands.remove_resource::<GameOverMenu>();
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn gameover_input(
+    mut menu: ResMut<GameOverMenu>,
+    players: Query<(Entity, &ActionState<PlayerAction>), With<Player>>,
+    mut app_state_next_state: ResMut<NextState<AppState>>,
+    // See `read_menu_input`, kept per player
+    mut sticks_tilted: Local<HashMap<Entity, bool>>,
+) {
+    for (entity, action_state) in players.iter() {
+        let stick_tilted = sticks_tilted.entry(entity).or_insert(true);
+        match read_menu_input(action_state, stick_tilted) {
+            Some(step @ (MenuInput::Up | MenuInput::Down)) => {
+                menu.selected = step_selection(menu.selected, GAME_OVER_OPTIONS.len(), step);
+                return;
+            }
+            Some(MenuInput::Confirm) => {
+                match GAME_OVER_OPTIONS[menu.selected] {
+                    GameOverOption::Rematch => {
+                        info!("Rematch!");
+                        app_state_next_state.set(AppState::InGame);
+                    }
+                    GameOverOption::BackToLobby => app_state_next_state.set(AppState::Menu),
+                }
+                sticks_tilted.clear();
+                return;
+            }
+            Some(MenuInput::Left | MenuInput::Right | MenuInput::Back) | None => {}
+        }
+    }
+}
+
+pub fn update_gameover_options(
+    menu: Res<GameOverMenu>,
+    mut texts: Query<(&GameOverOptionText, &mut Text)>,
+) {
+    if !menu.is_changed() {
+        return;
+    }
+
+    for (option, mut text) in texts.iter_mut() {
+        text.sections[0].style.color = if option.0 == menu.selected {
+            SELECTED_COLOR
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+fn build_gameover(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    stats: &MatchStats,
+    score: &MatchScore,
+) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..FULL_CENTER_COL
+                },
+                background_color: OVERLAY_COLOR.into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Game Over",
+                TextStyle {
+                    color: Color::WHITE,
+                    ..get_title_text_style(asset_server)
+                },
+            ));
+
+            let winner = match score.winner {
+                Some(winner) => format!("{winner} won the match"),
+                None => "Nobody won the match".to_string(),
+            };
+            spawn_button_text_box(asset_server, parent, &winner);
+            if score.results.len() > 1 {
+                let rounds: Vec<String> = score
+                    .wins
+                    .iter()
+                    .map(|(contender, wins)| format!("{contender} {wins}"))
+                    .collect();
+                spawn_button_text_box(
+                    asset_server,
+                    parent,
+                    &format!("Rounds won: {}", rounds.join(", ")),
+                );
+            }
+
+            // The font is monospaced, so padding lines the columns up
+            spawn_button_text_box(
+                asset_server,
+                parent,
+                &format!(
+                    "{:<4}{:>10}{:>8}{:>9}{:>12}{:>14}",
+                    "", "Survived", "Passes", "Catches", "Hits taken", "Damage dealt"
+                ),
+            );
+            for (player_id, player_stats) in stats.players.iter() {
+                spawn_button_text_box(
+                    asset_server,
+                    parent,
+                    &format!(
+                        "{:<4}{:>9.0}s{:>8}{:>9}{:>12}{:>14}",
+                        format!("P{}", player_id + 1),
+                        player_stats.survival_seconds,
+                        player_stats.passes,
+                        player_stats.catches,
+                        player_stats.lives_lost,
+                        player_stats.damage_dealt
+                    ),
+                );
+            }
+
+            for (index, option) in GAME_OVER_OPTIONS.iter().enumerate() {
+                let mut text_style = get_button_text_style(asset_server);
+                if index == 0 {
+                    text_style.color = SELECTED_COLOR;
+                }
+                parent.spawn((
+                    TextBundle::from_section(option.label(), text_style),
+                    GameOverOptionText(index),
+                ));
+            }
+
+            spawn_button_text_box(asset_server, parent, "Up/Down: select  Dash: confirm");
+        })
+        .id()
+}
@@ -1,4 +1,4 @@
-use crate::resources::{Player1Lives, Player2Lives};
+use crate::player::Player;
 use crate::ui::styles::get_hud_text_style;
 use crate::{AppState, GameState};
 use bevy::prelude::*;
@@ -83,20 +83,29 @@ fn build_hud(commands: &mut Commands, asset_server: &Res<AssetServer>) -> Entity
     hud
 }
 
+// The lives of the player with `player_id`, if they are in the match
+fn lives_of(players: &Query<&Player>, player_id: usize) -> Option<u32> {
+    players
+        .iter()
+        .find(|player| player.player_id == player_id)
+        .map(|player| player.lives)
+}
+
 pub fn update_lives(
     mut commands: Commands,
-    life: Res<Player1Lives>,
+    players: Query<&Player>,
     mut query: Query<&mut Text, With<LifeBox>>,
 ) {
     let mut text = query.single_mut(); //fails multiple entries
-    if life.lives == 0 {
+    let lives = lives_of(&players, 0).unwrap_or(0);
+    if lives == 0 {
         commands.insert_resource(NextState::Pending(GameState::Paused));
         commands.insert_resource(NextState::Pending(AppState::GameOver));
     }
-    text.sections[1].value = life.lives.to_string();
+    text.sections[1].value = lives.to_string();
 }
 
-pub fn update_score(score: Res<Player2Lives>, mut query: Query<&mut Text, With<ScoreBoard>>) {
+pub fn update_score(players: Query<&Player>, mut query: Query<&mut Text, With<ScoreBoard>>) {
     let mut text = query.single_mut();
-    text.sections[1].value = score.lives.to_string();
+    text.sections[1].value = lives_of(&players, 1).unwrap_or(0).to_string();
 }
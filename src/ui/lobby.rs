@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+
+use crate::lobby::{LobbyCountdown, LobbySlot};
+use crate::player::{Appearance, Player};
+use crate::resources::InputSource;
+use crate::ui::styles::*;
+use crate::ui::PlayerBox;
+
+const READY_COLOR: Color = Color::srgb(0.35, 0.75, 0.35);
+const BOX_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+// Holds a box for every joined player
+#[derive(Component)]
+pub struct PlayerBoxRow;
+
+#[derive(Component)]
+pub struct LobbyStatusText;
+
+// Gives every player seated in the lobby their box. The slot stays on the
+// player while the lobby is open, so each player only ever gets one box
+pub fn add_player_boxes(
+    mut commands: Commands,
+    new_players: Query<Entity, Added<LobbySlot>>,
+    row_query: Query<Entity, With<PlayerBoxRow>>,
+) {
+    let Ok(row) = row_query.get_single() else {
+        return;
+    };
+
+    for entity in new_players.iter() {
+        let player_box = commands
+            .spawn((
+                NodeBundle {
+                    style: PLAYER_BOX_STYLE,
+                    background_color: BOX_COLOR.into(),
+                    ..default()
+                },
+                PlayerBox { player: entity },
+            ))
+            .id();
+        commands.entity(row).add_child(player_box);
+    }
+}
+
+pub fn despawn_player_boxes(
+    mut commands: Commands,
+    player_box_query: Query<(Entity, &PlayerBox)>,
+    players: Query<(), With<Player>>,
+) {
+    for (entity, player_box) in player_box_query.iter() {
+        if !players.contains(player_box.player) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// Redraws a box whenever its player changes their picks or readiness
+pub fn refresh_player_boxes(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    player_box_query: Query<(Entity, &PlayerBox)>,
+    players: Query<(&Player, Ref<Appearance>, Ref<LobbySlot>)>,
+) {
+    for (entity, player_box) in player_box_query.iter() {
+        let Ok((player, appearance, slot)) = players.get(player_box.player) else {
+            continue;
+        };
+        if !appearance.is_changed() && !slot.is_changed() {
+            continue;
+        }
+
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                build_player_box(parent, &asset_server, player, &appearance, &slot);
+            });
+    }
+}
+
+pub fn update_lobby_status(
+    countdown: Option<Res<LobbyCountdown>>,
+    players: Query<(), With<Player>>,
+    mut texts: Query<&mut Text, With<LobbyStatusText>>,
+) {
+    let status = match countdown {
+        Some(countdown) => format!("Starting in {}..", countdown.0.remaining_secs().ceil()),
+        None if players.is_empty() => "Waiting for players to join".to_string(),
+        None => "Waiting for everyone to be ready".to_string(),
+    };
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
+
+fn build_player_box(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    player: &Player,
+    appearance: &Appearance,
+    slot: &LobbySlot,
+) {
+    let text_style = get_button_text_style(asset_server);
+    let bot = matches!(player.input_source, InputSource::Bot(_));
+
+    let title = if bot {
+        format!("P{} (bot)", player.player_id + 1)
+    } else {
+        format!("P{}", player.player_id + 1)
+    };
+    parent.spawn(TextBundle::from_section(
+        title,
+        TextStyle {
+            color: appearance.color(),
+            ..text_style.clone()
+        },
+    ));
+    parent.spawn(TextBundle::from_section(
+        format!("< {} >", appearance.color_name()),
+        text_style.clone(),
+    ));
+    parent.spawn(TextBundle::from_section(
+        format!("^ {} v", appearance.character.name()),
+        text_style.clone(),
+    ));
+
+    let (ready_text, ready_color) = if slot.ready {
+        ("Ready!", READY_COLOR)
+    } else {
+        ("Not ready", Color::WHITE)
+    };
+    parent.spawn(TextBundle::from_section(
+        ready_text,
+        TextStyle {
+            color: ready_color,
+            ..text_style
+        },
+    ));
+}
@@ -1,51 +1,143 @@
 mod buttons;
+mod controls;
 mod gameover;
 mod hud;
+mod lobby;
 mod menu;
+mod pause_menu;
+mod reconnect;
+mod replay_viewer;
+mod round_scoreboard;
 mod start;
 mod start_countdown;
 mod styles;
 
-use crate::resources::CountdownTimer;
-use crate::ui::start::{despawn_start_menu, spawn_start_menu, start};
-use crate::ui::start_countdown::countdown;
+use crate::lobby::LobbySet;
+use crate::pause::PauseSet;
+use crate::replay::ReplayPlayback;
+use crate::resources::CurrentMap;
+use crate::ui::controls::{
+    close_controls_screen, despawn_controls_screen, navigate_controls_screen, open_controls_screen,
+    open_pause_settings, refresh_controls_screen, RemapSession,
+};
+use crate::ui::gameover::{
+    despawn_gameover, gameover_input, spawn_gameover, update_gameover_options,
+};
+use crate::ui::lobby::{
+    add_player_boxes, despawn_player_boxes, refresh_player_boxes, update_lobby_status,
+};
+use crate::ui::pause_menu::{close_pause_menu, refresh_pause_menu};
+use crate::ui::reconnect::{clear_reconnect_overlay, update_reconnect_overlay};
+use crate::ui::replay_viewer::{spawn_replay_viewer, update_replay_viewer};
+use crate::ui::round_scoreboard::{clear_round_scoreboard, update_round_scoreboard};
+use crate::ui::start::{despawn_start_menu, spawn_start_menu, update_lobby_settings};
+use crate::ui::start_countdown::show_countdown;
 use crate::AppState;
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
-
-use self::start::{add_player_boxes, despawn_player_boxes};
 mod helpers;
 
 #[derive(Component, Debug)]
 struct GameBackground;
 
+// A joined player's box in the lobby
 #[derive(Component, Debug)]
 pub struct PlayerBox {
-    pub player_id: usize,
+    pub player: Entity,
 }
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<CountdownTimer>()
-            .add_plugins(TilemapPlugin)
+        app.add_plugins(TilemapPlugin)
             .add_plugins(helpers::tiled::TiledMapPlugin)
             .add_systems(Startup, spawn_game_background)
             .add_systems(OnEnter(AppState::Menu), spawn_start_menu)
-            .add_systems(OnExit(AppState::Menu), despawn_start_menu)
-            // .add_systems(
-            //     Update,
-            //     (add_player_boxes, despawn_player_boxes).run_if(in_state(AppState::Menu)),
-            // )
+            .add_systems(
+                OnExit(AppState::Menu),
+                (
+                    despawn_start_menu,
+                    close_controls_screen,
+                    despawn_controls_screen,
+                ),
+            )
             // .add_systems(OnEnter(AppState::InGame), spawn_hud)
             // .add_systems(OnExit(AppState::InGame), despawn_hud)
-            // .add_systems(OnEnter(AppState::GameOver), spawn_gameover)
-            // .add_systems(OnExit(AppState::GameOver), despawn_gameover)
-            .add_systems(Update, (toggle_appstate,))
-            .add_systems(Update, (countdown).run_if(in_state(AppState::InGame)))
+            .add_systems(OnEnter(AppState::GameOver), spawn_gameover)
+            .add_systems(OnExit(AppState::GameOver), despawn_gameover)
+            .add_systems(
+                Update,
+                (gameover_input, update_gameover_options)
+                    .chain()
+                    .run_if(in_state(AppState::GameOver)),
+            )
+            .add_systems(
+                Update,
+                (
+                    show_countdown,
+                    update_reconnect_overlay,
+                    update_round_scoreboard,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            )
+            // The pause menu waits while someone is changing their controls
+            .configure_sets(
+                Update,
+                PauseSet
+                    .run_if(not(resource_exists::<RemapSession>))
+                    .after(navigate_controls_screen),
+            )
+            .add_systems(
+                Update,
+                (
+                    navigate_controls_screen.run_if(resource_exists::<RemapSession>),
+                    (open_pause_settings, refresh_pause_menu).after(PauseSet),
+                    refresh_controls_screen,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                OnExit(AppState::InGame),
+                (
+                    clear_reconnect_overlay,
+                    clear_round_scoreboard,
+                    close_pause_menu,
+                    close_controls_screen,
+                    despawn_controls_screen,
+                ),
+            )
+            .add_systems(
+                Startup,
+                spawn_replay_viewer.run_if(resource_exists::<ReplayPlayback>),
+            )
+            .add_systems(
+                Update,
+                update_replay_viewer.run_if(resource_exists::<ReplayPlayback>),
+            )
             // .add_systems(Update, (update_score).run_if(in_state(AppState::GameOver)))
-            .add_systems(Update, (start).run_if(in_state(AppState::Menu)));
+            // The lobby waits while someone is changing their controls
+            .configure_sets(
+                Update,
+                LobbySet.run_if(not(resource_exists::<RemapSession>)),
+            )
+            .add_systems(
+                Update,
+                (
+                    add_player_boxes,
+                    despawn_player_boxes,
+                    open_controls_screen.run_if(not(resource_exists::<RemapSession>)),
+                    navigate_controls_screen.run_if(resource_exists::<RemapSession>),
+                    refresh_player_boxes,
+                    update_lobby_status,
+                    refresh_controls_screen,
+                    update_lobby_settings,
+                )
+                    .chain()
+                    .after(LobbySet)
+                    .run_if(in_state(AppState::Menu)),
+            );
 
         // .add_systems(
         //     Update,
@@ -55,8 +147,12 @@ impl Plugin for UiPlugin {
     }
 }
 
-fn spawn_game_background(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let map_handle: Handle<helpers::tiled::TiledMap> = asset_server.load("map.tmx");
+fn spawn_game_background(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_map: Res<CurrentMap>,
+) {
+    let map_handle: Handle<helpers::tiled::TiledMap> = asset_server.load(current_map.0.clone());
 
     commands.spawn((
         helpers::tiled::TiledMapBundle {
@@ -75,18 +171,3 @@ fn _despawn_game_background(
         commands.entity(entity).despawn_recursive();
     }
 }
-
-fn toggle_appstate(
-    mut commands: Commands,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    game_state: Res<State<AppState>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::KeyM) && game_state.as_ref() == &AppState::InGame {
-        println!("Appstate set to: Menu");
-        commands.insert_resource(NextState::Pending(AppState::Menu));
-    }
-    if keyboard_input.just_pressed(KeyCode::KeyM) && game_state.as_ref() == &AppState::Menu {
-        println!("Appstate set to: InGame");
-        commands.insert_resource(NextState::Pending(AppState::InGame));
-    }
-}
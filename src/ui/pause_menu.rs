@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+use crate::pause::PAUSE_OPTIONS;
+use crate::resources::PauseMenu;
+use crate::rounds::RoundClock;
+use crate::ui::styles::*;
+
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const DISABLED_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+
+#[derive(Component)]
+pub struct PauseScreen;
+
+pub fn refresh_pause_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu: Option<Res<PauseMenu>>,
+    screen_query: Query<Entity, With<PauseScreen>>,
+    clock: Res<RoundClock>,
+    // Whether the menu was last drawn during the intermission
+    mut drawn_in_intermission: Local<bool>,
+) {
+    let menu_changed = match &menu {
+        Some(menu) => menu.is_changed() || *drawn_in_intermission != clock.in_intermission(),
+        None => !screen_query.is_empty(),
+    };
+    if !menu_changed {
+        return;
+    }
+
+    despawn_pause_screen(&mut commands, &screen_query);
+    if let Some(menu) = menu {
+        build_pause_screen(&mut commands, &asset_server, &menu, &clock);
+        *drawn_in_intermission = clock.in_intermission();
+    }
+}
+
+pub fn close_pause_menu(mut commands: Commands, screen_query: Query<Entity, With<PauseScreen>>) {
+    despawn_pause_screen(&mut commands, &screen_query);
+}
+
+fn despawn_pause_screen(commands: &mut Commands, screen_query: &Query<Entity, With<PauseScreen>>) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn build_pause_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    menu: &PauseMenu,
+    clock: &RoundClock,
+) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..FULL_CENTER_COL
+                },
+                background_color: OVERLAY_COLOR.into(),
+                // Below the reconnect overlay and the controls screen
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            PauseScreen,
+        ))
+        .with_children(|parent| {
+            spawn_button_text_box(
+                asset_server,
+                parent,
+                &format!("Paused by P{}", menu.player_id + 1),
+            );
+
+            for (index, option) in PAUSE_OPTIONS.iter().enumerate() {
+                let mut text_style = get_button_text_style(asset_server);
+                if index == menu.selected {
+                    text_style.color = SELECTED_COLOR;
+                } else if !option.is_enabled(clock) {
+                    text_style.color = DISABLED_COLOR;
+                }
+                parent.spawn(TextBundle::from_section(option.label(), text_style));
+            }
+
+            spawn_button_text_box(
+                asset_server,
+                parent,
+                "Up/Down: select  Dash: confirm  Start: resume",
+            );
+        })
+        .id()
+}
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use crate::gamepad::AwaitingReconnect;
+use crate::player::Player;
+use crate::ui::styles::*;
+
+const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+#[derive(Component)]
+pub struct ReconnectOverlay;
+
+pub fn update_reconnect_overlay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    awaiting_players: Query<&Player, With<AwaitingReconnect>>,
+    newly_awaiting: Query<(), Added<AwaitingReconnect>>,
+    mut no_longer_awaiting: RemovedComponents<AwaitingReconnect>,
+    overlay_query: Query<Entity, With<ReconnectOverlay>>,
+) {
+    if newly_awaiting.is_empty() && no_longer_awaiting.read().count() == 0 {
+        return;
+    }
+
+    despawn_reconnect_overlay(&mut commands, &overlay_query);
+    if awaiting_players.is_empty() {
+        return;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..FULL_CENTER_COL
+                },
+                background_color: OVERLAY_COLOR.into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            ReconnectOverlay,
+        ))
+        .with_children(|parent| {
+            for player in awaiting_players.iter() {
+                spawn_button_text_box(
+                    &asset_server,
+                    parent,
+                    &format!("Reconnect P{}", player.player_id + 1),
+                );
+            }
+            spawn_button_text_box(
+                &asset_server,
+                parent,
+                "Press Start to continue without them",
+            );
+        });
+}
+
+pub fn clear_reconnect_overlay(
+    mut commands: Commands,
+    overlay_query: Query<Entity, With<ReconnectOverlay>>,
+) {
+    despawn_reconnect_overlay(&mut commands, &overlay_query);
+}
+
+fn despawn_reconnect_overlay(
+    commands: &mut Commands,
+    overlay_query: &Query<Entity, With<ReconnectOverlay>>,
+) {
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+
+use crate::replay::ReplayPlayback;
+use crate::ui::styles::get_hud_text_style;
+
+#[derive(Component)]
+pub struct ReplayViewerText;
+
+pub fn spawn_replay_viewer(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", get_hud_text_style(&asset_server)).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        ReplayViewerText,
+    ));
+}
+
+pub fn update_replay_viewer(
+    playback: Res<ReplayPlayback>,
+    time: Res<Time<Virtual>>,
+    mut text_query: Query<&mut Text, With<ReplayViewerText>>,
+) {
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+
+    let status = if playback.is_finished() {
+        "end of replay".to_string()
+    } else if time.is_paused() {
+        "paused".to_string()
+    } else {
+        format!("{}x", time.relative_speed())
+    };
+    text.sections[0].value = format!(
+        "Replay {}/{} ({})  P: pause  -/=: speed  hold .: fast forward",
+        playback.next_tick,
+        playback.replay.ticks.len(),
+        status,
+    );
+}
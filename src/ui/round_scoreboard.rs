@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::match_setup::MatchConfig;
+use crate::rounds::{MatchScore, RoundClock};
+use crate::ui::styles::*;
+
+const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+#[derive(Component)]
+pub struct RoundScoreboard;
+
+// Shown for as long as the intermission between two rounds lasts
+pub fn update_round_scoreboard(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    clock: Res<RoundClock>,
+    score: Res<MatchScore>,
+    config: Res<MatchConfig>,
+    scoreboard_query: Query<Entity, With<RoundScoreboard>>,
+) {
+    if clock.in_intermission() == !scoreboard_query.is_empty() {
+        return;
+    }
+
+    despawn_round_scoreboard(&mut commands, &scoreboard_query);
+    if !clock.in_intermission() {
+        return;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..FULL_CENTER_COL
+                },
+                background_color: OVERLAY_COLOR.into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            RoundScoreboard,
+        ))
+        .with_children(|parent| {
+            if let Some(outcome) = score.results.last() {
+                spawn_button_text_box(
+                    &asset_server,
+                    parent,
+                    &format!("Round {}: {}", score.round, outcome),
+                );
+            }
+            for (contender, wins) in score.wins.iter() {
+                spawn_button_text_box(
+                    &asset_server,
+                    parent,
+                    &format!("{contender}: {wins} / {}", config.rules.rounds_to_win),
+                );
+            }
+            spawn_button_text_box(&asset_server, parent, "Next round coming up");
+        });
+}
+
+pub fn clear_round_scoreboard(
+    mut commands: Commands,
+    scoreboard_query: Query<Entity, With<RoundScoreboard>>,
+) {
+    despawn_round_scoreboard(&mut commands, &scoreboard_query);
+}
+
+fn despawn_round_scoreboard(
+    commands: &mut Commands,
+    scoreboard_query: &Query<Entity, With<RoundScoreboard>>,
+) {
+    for entity in scoreboard_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
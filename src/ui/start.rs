@@ -1,108 +1,77 @@
-use crate::gamepad::PlayerAction;
+use crate::bot::{Bot, BotSettings};
+use crate::lobby::{LobbySetting, LobbySettings};
+use crate::match_setup::MatchConfig;
 use crate::player::Player;
-use crate::resources::{CountdownTimer, JoinedPlayers};
+use crate::ui::lobby::{LobbyStatusText, PlayerBoxRow};
 use crate::ui::styles::*;
-use crate::AppState;
-use bevy::app::AppExit;
 use bevy::prelude::*;
-use bevy::prelude::*;
-use leafwing_input_manager::prelude::ActionState;
 
-use super::PlayerBox;
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
 
 #[derive(Component)]
 pub struct StartMenu;
 
+// One line of the match settings list
+#[derive(Component)]
+pub struct LobbySettingText(LobbySetting);
+
 pub fn spawn_start_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
     build_start_menu(&mut commands, &asset_server);
 }
 
+// The player boxes are part of the menu, so they go with it
 pub fn despawn_start_menu(
     mut commands: Commands,
     start_menu_query: Query<Entity, With<StartMenu>>,
-    player_box_query: Query<Entity, With<PlayerBox>>,
 ) {
     if let Ok(start_menu_entity) = start_menu_query.get_single() {
         commands.entity(start_menu_entity).despawn_recursive();
     }
-    for entity in player_box_query.iter() {
-        commands.entity(entity).despawn_recursive();
-    }
 }
 
-pub fn start(
-    mut countdown: ResMut<CountdownTimer>,
-    start_action: Query<&ActionState<PlayerAction>>,
-    mut app_state_next_state: ResMut<NextState<AppState>>,
-) {
-    for start in start_action.iter() {
-        if start.pressed(&PlayerAction::Start) {
-            countdown.duration = 4;
-            app_state_next_state.set(AppState::InGame);
-        }
-    }
-}
-
-pub fn add_player_boxes(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+// The selected setting is highlighted while someone is changing the settings
+pub fn update_lobby_settings(
+    lobby_settings: Res<LobbySettings>,
+    bot_settings: Res<BotSettings>,
+    config: Res<MatchConfig>,
+    bots: Query<(), With<Bot>>,
     players: Query<&Player>,
-    mut start_menu_query: Query<Entity, With<StartMenu>>,
+    mut texts: Query<(&LobbySettingText, &mut Text)>,
 ) {
-    if players.is_empty() || start_menu_query.is_empty() {
-        return;
-    }
-
-    let start_menu_entity = start_menu_query.single_mut();
-
-    // only spawn if there is no playerbox for that player
-
-    for player in players.iter() {
-        let playerid = player.player_id + 1;
-        let player_box = commands
-            .spawn((
-                NodeBundle {
-                    style: BUTTON_STYLE,
-                    ..default()
-                },
-                PlayerBox {
-                    player_id: player.player_id,
-                },
-            ))
-            .with_children(|parent| {
-                parent.spawn(TextBundle {
-                    text: Text {
-                        sections: vec![TextSection::new(
-                            format!("P {}", playerid.to_string()),
-                            get_button_text_style(&asset_server),
-                        )],
-                        justify: JustifyText::Center,
-                        ..default()
-                    },
-                    ..default()
-                });
-            })
-            .id();
-        commands.entity(start_menu_entity).add_child(player_box);
-    }
-}
+    let editor = lobby_settings
+        .editor
+        .and_then(|editor| players.get(editor).ok());
 
-pub fn despawn_player_boxes(
-    mut commands: Commands,
-    player_box_query: Query<(Entity, &PlayerBox)>,
-    player_query: Query<&Player>,
-) {
-    if player_box_query.is_empty() {
-        return;
-    }
-    for (entity, player_box) in player_box_query.iter() {
-        if player_query
-            .iter()
-            .any(|player| player.player_id == player_box.player_id)
-        {
-            continue;
+    for (LobbySettingText(setting), mut text) in texts.iter_mut() {
+        let value = match setting {
+            LobbySetting::Bots => bots.iter().count().to_string(),
+            LobbySetting::BotSkill => format!("{:?}", bot_settings.skill),
+            LobbySetting::RoundsToWin => config.rules.rounds_to_win.to_string(),
+            LobbySetting::RoundTime => match config.rules.round_seconds {
+                Some(seconds) => format!("{seconds}s"),
+                None => "no limit".to_string(),
+            },
+            LobbySetting::Mode => format!("{:?}", config.rules.mode),
+        };
+        let selected = LobbySetting::ALL.get(lobby_settings.selected) == Some(setting);
+        let (line, color) = match editor {
+            Some(editor) if selected => (
+                format!(
+                    "P{}: < {}: {} >",
+                    editor.player_id + 1,
+                    setting.name(),
+                    value
+                ),
+                SELECTED_COLOR,
+            ),
+            _ => (format!("{}: {}", setting.name(), value), Color::WHITE),
+        };
+        if text.sections[0].value != line {
+            text.sections[0].value = line;
+        }
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
         }
-        commands.entity(entity).despawn_recursive();
     }
 }
 
@@ -131,7 +100,35 @@ fn build_start_menu(commands: &mut Commands, asset_server: &Res<AssetServer>) ->
                     parent.spawn(get_chicken_image_bundle(asset_server));
                 });
             //Title
-            spawn_title_box(asset_server, parent, "Press L1 + R1 to to join");
+            spawn_title_box(asset_server, parent, "Press L1 + R1 to join");
+            spawn_button_text_box(
+                asset_server,
+                parent,
+                "Keyboard: Enter, Q + E or RShift + RCtrl",
+            );
+            spawn_button_text_box(asset_server, parent, "Press Y to change your controls");
+            parent.spawn((
+                NodeBundle {
+                    style: PLAYER_BOX_ROW_STYLE,
+                    ..default()
+                },
+                PlayerBoxRow,
+            ));
+            spawn_button_text_box(
+                asset_server,
+                parent,
+                "Left/Right: color  Up/Down: character  Dash/Start: ready  Grab: settings",
+            );
+            parent.spawn((
+                TextBundle::from_section("", get_button_text_style(asset_server)),
+                LobbyStatusText,
+            ));
+            for setting in LobbySetting::ALL {
+                parent.spawn((
+                    TextBundle::from_section("", get_button_text_style(asset_server)),
+                    LobbySettingText(setting),
+                ));
+            }
         })
         .id();
     start_menu_entity
@@ -1,38 +1,28 @@
 use bevy::prelude::*;
 
-use crate::resources::CountdownTimer;
+use crate::simulation::CountdownAdvanced;
 use crate::ui::styles::spawn_title_box;
-use crate::GameState;
 
 #[derive(Component, Debug)]
 pub struct Countdown;
 
-pub fn countdown(
+// Shows the countdown driven by the simulation, `CountdownAdvanced` carries the steps left
+pub fn show_countdown(
     mut commands: Commands,
-    time: Res<Time>,
-    mut timer: ResMut<CountdownTimer>,
+    mut countdown_advanced: EventReader<CountdownAdvanced>,
     asset_server: Res<AssetServer>,
     countdown_query: Query<Entity, With<Countdown>>,
 ) {
-    timer.timer.tick(time.delta());
-    if timer.timer.just_finished() {
-        if timer.duration == 4 {
+    for CountdownAdvanced(duration) in countdown_advanced.read() {
+        despawn_countdown(&mut commands, &countdown_query);
+        if *duration == 4 {
             spawn_countdown(&mut commands, &asset_server, "3..");
-        } else if timer.duration == 3 {
-            despawn_countdown(&mut commands, &countdown_query);
+        } else if *duration == 3 {
             spawn_countdown(&mut commands, &asset_server, "2..");
-        } else if timer.duration == 2 {
-            despawn_countdown(&mut commands, &countdown_query);
+        } else if *duration == 2 {
             spawn_countdown(&mut commands, &asset_server, "1..");
-        } else if timer.duration == 1 {
-            despawn_countdown(&mut commands, &countdown_query);
+        } else if *duration == 1 {
             spawn_countdown(&mut commands, &asset_server, "GO..");
-            commands.insert_resource(NextState::Pending(GameState::Running));
-        } else if timer.duration == 0 {
-            despawn_countdown(&mut commands, &countdown_query);
-        }
-        if timer.duration != 0 {
-            timer.duration -= 1;
         }
     }
 }
@@ -47,6 +47,27 @@ pub const CENTER_ROW: Style = {
     style.height = Val::Px(120.0);
     style
 };
+pub const PLAYER_BOX_ROW_STYLE: Style = {
+    let mut style = Style::DEFAULT;
+    style.flex_direction = FlexDirection::Row;
+    style.justify_content = JustifyContent::Center;
+    style.align_items = AlignItems::Center;
+    style.column_gap = Val::Px(20.0);
+    style.width = Val::Percent(100.0);
+    style
+};
+
+pub const PLAYER_BOX_STYLE: Style = {
+    let mut style = Style::DEFAULT;
+    style.flex_direction = FlexDirection::Column;
+    style.justify_content = JustifyContent::Center;
+    style.align_items = AlignItems::Center;
+    style.row_gap = Val::Px(8.0);
+    style.width = Val::Px(240.0);
+    style.height = Val::Px(200.0);
+    style
+};
+
 pub const FULL_CENTER_COL: Style = {
     let mut style = Style::DEFAULT;
     style.flex_direction = FlexDirection::Column;
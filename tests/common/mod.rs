@@ -0,0 +1,361 @@
+// Drives the game headless, one fixed tick per update, with the player input
+// written from the test instead of devices
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::RunSystemOnce,
+    input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadInfo, GamepadRumbleRequest},
+        keyboard::{Key, KeyboardInput, NativeKey},
+        ButtonState,
+    },
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use birdstomp::{
+    bot::{spawn_bot, BotSkill},
+    enemy::Enemy,
+    gamepad::PlayerAction,
+    headless,
+    input_buffer::{buffer_just_pressed, FixedInputSet, FixedTick, InputBuffer},
+    player::{spawn_player, Player},
+    replay::{self, Replay, ReplayRecording},
+    resources::{InputSource, JoinedPlayers, MatchSeed},
+    simulation::{InterpolatedTranslation, FIXED_TIMESTEP},
+    tuning::{Tuning, TuningOverride},
+    AppState, BirdstompSettings, GameState,
+};
+use leafwing_input_manager::prelude::*;
+
+pub struct TestGame {
+    pub app: App,
+}
+
+// Input changes queued by the test, written to the action states at the start of the next tick
+#[derive(Resource, Default)]
+struct ScriptedInput(Vec<ScriptedAction>);
+
+// Every rumble request sent since the test last took them, there are no gamepads to play them
+#[derive(Resource, Default)]
+struct RumbleRequests(Vec<GamepadRumbleRequest>);
+
+enum ScriptedAction {
+    Press {
+        player: Entity,
+        action: PlayerAction,
+        axis_pair: Option<Vec2>,
+    },
+    Release {
+        player: Entity,
+        action: PlayerAction,
+    },
+}
+
+impl TestGame {
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let mut app = headless::app(BirdstompSettings {
+            replays: false,
+            ..BirdstompSettings::headless()
+        });
+        app.insert_resource(MatchSeed(seed));
+        Self::from_app(app)
+    }
+
+    // Plays with `tuning` instead of the tuning file
+    pub fn with_tuning(tuning: Tuning) -> Self {
+        let mut app = headless::app(BirdstompSettings {
+            replays: false,
+            ..BirdstompSettings::headless()
+        });
+        app.insert_resource(MatchSeed(0))
+            .insert_resource(TuningOverride(tuning));
+        Self::from_app(app)
+    }
+
+    // Records every match like the game does, see `recorded_replay`
+    pub fn with_replays() -> Self {
+        let mut app = headless::app(BirdstompSettings {
+            replays: true,
+            ..BirdstompSettings::headless()
+        });
+        app.insert_resource(MatchSeed(0));
+        Self::from_app(app)
+    }
+
+    // Plays `replay` back, which starts the match on its own
+    pub fn playing_back(replay: Replay) -> Self {
+        let mut app = headless::app(BirdstompSettings {
+            replays: false,
+            ..BirdstompSettings::headless()
+        });
+        replay::play_back(&mut app, replay);
+        Self::from_app(app)
+    }
+
+    fn from_app(mut app: App) -> Self {
+        app.init_resource::<ScriptedInput>()
+            .init_resource::<RumbleRequests>()
+            .add_systems(FixedPreUpdate, apply_scripted_input.in_set(FixedInputSet))
+            .add_systems(Last, record_rumble_requests);
+        app.finish();
+        app.cleanup();
+        // Runs the startup schedules
+        app.update();
+
+        Self { app }
+    }
+
+    pub fn tick(&mut self) {
+        self.app.update();
+    }
+
+    // Runs `ticks` fixed ticks in a single update, like a slow frame does
+    pub fn tick_in_one_frame(&mut self, ticks: u32) {
+        self.set_frame_time(ticks as f32 * FIXED_TIMESTEP);
+        self.app.update();
+        self.set_frame_time(FIXED_TIMESTEP);
+    }
+
+    pub fn tick_seconds(&mut self, seconds: f32) {
+        for _ in 0..ticks_in(seconds) {
+            self.tick();
+        }
+    }
+
+    // Ticks until `condition` holds, returns false if it didn't within `seconds`
+    pub fn tick_until(
+        &mut self,
+        seconds: f32,
+        mut condition: impl FnMut(&mut World) -> bool,
+    ) -> bool {
+        for _ in 0..ticks_in(seconds) {
+            self.tick();
+            if condition(self.app.world_mut()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Goes from the menu into a match the way the menu does, countdown included
+    pub fn start_match(&mut self) {
+        self.app
+            .world_mut()
+            .insert_resource(NextState::Pending(AppState::InGame));
+        self.tick();
+    }
+
+    // Starts a match and waits for the countdown to finish
+    pub fn start_running_match(&mut self) {
+        self.start_match();
+        let running = self.tick_until(10.0, |world| {
+            world.resource::<State<GameState>>().get() == &GameState::Running
+        });
+        assert!(running, "the countdown never finished");
+    }
+
+    // Adds a player that only moves through the scripted input
+    pub fn spawn_player(&mut self, player_id: usize) -> Entity {
+        let input_source = InputSource::Gamepad(Gamepad::new(player_id));
+        self.app.world_mut().run_system_once(
+            move |mut commands: Commands, mut joined_players: ResMut<JoinedPlayers>| {
+                let player =
+                    spawn_player(&mut commands, InputMap::default(), input_source, player_id);
+                joined_players.0.insert(input_source, player);
+                player
+            },
+        )
+    }
+
+    pub fn spawn_bot(&mut self, skill: BotSkill) -> Entity {
+        self.app.world_mut().run_system_once(
+            move |mut commands: Commands,
+                  mut joined_players: ResMut<JoinedPlayers>,
+                  players: Query<&Player>| {
+                spawn_bot(&mut commands, &mut joined_players, &players, skill)
+            },
+        )
+    }
+
+    pub fn press(&mut self, player: Entity, action: PlayerAction) {
+        self.script(ScriptedAction::Press {
+            player,
+            action,
+            axis_pair: None,
+        });
+    }
+
+    // Presses and lets go again, so the next press counts as a new one
+    pub fn tap(&mut self, player: Entity, action: PlayerAction) {
+        self.press(player, action);
+        self.tick();
+        self.release(player, action);
+        self.tick();
+    }
+
+    // Holds a stick action in `direction` until it is released
+    pub fn hold_axis(&mut self, player: Entity, action: PlayerAction, direction: Vec2) {
+        self.script(ScriptedAction::Press {
+            player,
+            action,
+            axis_pair: Some(direction),
+        });
+    }
+
+    pub fn release(&mut self, player: Entity, action: PlayerAction) {
+        self.script(ScriptedAction::Release { player, action });
+    }
+
+    // Presses a key the way a keyboard would, for the parts of the game that read devices
+    pub fn press_key(&mut self, key_code: KeyCode) {
+        self.send_key(key_code, ButtonState::Pressed);
+    }
+
+    pub fn release_key(&mut self, key_code: KeyCode) {
+        self.send_key(key_code, ButtonState::Released);
+    }
+
+    // Plugs a gamepad in or out the way the gamepad backend reports it
+    pub fn connect_gamepad(&mut self, id: usize) {
+        self.app.world_mut().send_event(GamepadConnectionEvent::new(
+            Gamepad::new(id),
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Test gamepad".into(),
+            }),
+        ));
+    }
+
+    pub fn disconnect_gamepad(&mut self, id: usize) {
+        self.app.world_mut().send_event(GamepadConnectionEvent::new(
+            Gamepad::new(id),
+            GamepadConnection::Disconnected,
+        ));
+    }
+
+    pub fn player(&self, player: Entity) -> &Player {
+        self.app
+            .world()
+            .get::<Player>(player)
+            .expect("the player was despawned")
+    }
+
+    // The match recorded so far, only there with `with_replays`
+    pub fn recorded_replay(&self) -> Replay {
+        self.app
+            .world()
+            .resource::<ReplayRecording>()
+            .replay()
+            .clone()
+    }
+
+    pub fn player_mut(&mut self, player: Entity) -> Mut<Player> {
+        self.app
+            .world_mut()
+            .get_mut::<Player>(player)
+            .expect("the player was despawned")
+    }
+
+    // Moves a player without going through the simulation
+    pub fn teleport(&mut self, player: Entity, position: Vec2) {
+        let translation = position.extend(0.0);
+        let mut entity = self.app.world_mut().entity_mut(player);
+        entity.get_mut::<Transform>().unwrap().translation = translation;
+        entity.insert(InterpolatedTranslation::new(translation));
+    }
+
+    // The enemy of the running match
+    pub fn enemy(&mut self) -> Entity {
+        let world = self.app.world_mut();
+        world.query_filtered::<Entity, With<Enemy>>().single(world)
+    }
+
+    pub fn count<T: Component>(&mut self) -> usize {
+        let world = self.app.world_mut();
+        world.query_filtered::<(), With<T>>().iter(world).count()
+    }
+
+    pub fn take_rumble_requests(&mut self) -> Vec<GamepadRumbleRequest> {
+        std::mem::take(&mut self.app.world_mut().resource_mut::<RumbleRequests>().0)
+    }
+
+    pub fn app_state(&self) -> AppState {
+        *self.app.world().resource::<State<AppState>>().get()
+    }
+
+    pub fn game_state(&self) -> GameState {
+        *self.app.world().resource::<State<GameState>>().get()
+    }
+
+    fn set_frame_time(&mut self, seconds: f32) {
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                seconds,
+            )));
+    }
+
+    fn script(&mut self, action: ScriptedAction) {
+        self.app
+            .world_mut()
+            .resource_mut::<ScriptedInput>()
+            .0
+            .push(action);
+    }
+
+    fn send_key(&mut self, key_code: KeyCode, state: ButtonState) {
+        self.app.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+}
+
+fn ticks_in(seconds: f32) -> u32 {
+    (seconds / FIXED_TIMESTEP).ceil() as u32
+}
+
+// Runs right before the gameplay systems, so presses are still just pressed when they read them
+fn apply_scripted_input(
+    mut scripted: ResMut<ScriptedInput>,
+    mut players: Query<(&mut ActionState<PlayerAction>, &mut InputBuffer)>,
+    tick: Res<FixedTick>,
+) {
+    for scripted_action in scripted.0.drain(..) {
+        match scripted_action {
+            ScriptedAction::Press {
+                player,
+                action,
+                axis_pair,
+            } => {
+                let Ok((mut action_state, mut input_buffer)) = players.get_mut(player) else {
+                    continue;
+                };
+                action_state.press(&action);
+                if let Some(direction) = axis_pair {
+                    action_state.action_data_mut_or_default(&action).axis_pair =
+                        Some(DualAxisData::from_xy(direction));
+                }
+                buffer_just_pressed(&action_state, &mut input_buffer, &tick);
+            }
+            ScriptedAction::Release { player, action } => {
+                if let Ok((mut action_state, _)) = players.get_mut(player) {
+                    action_state.release(&action);
+                }
+            }
+        }
+    }
+}
+
+fn record_rumble_requests(
+    mut rumble_requests: EventReader<GamepadRumbleRequest>,
+    mut recorded: ResMut<RumbleRequests>,
+) {
+    recorded.0.extend(rumble_requests.read().cloned());
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use bevy::input::gamepad::{Gamepad, GamepadRumbleIntensity, GamepadRumbleRequest};
+use birdstomp::feedback::{rumble_for, FeedbackKind, RumbleIntensity};
+
+const GAMEPAD: Gamepad = Gamepad { id: 0 };
+
+fn rumble(kind: FeedbackKind, rumble_intensity: f32) -> Option<(GamepadRumbleIntensity, Duration)> {
+    match rumble_for(kind, GAMEPAD, RumbleIntensity(rumble_intensity))? {
+        GamepadRumbleRequest::Add {
+            gamepad,
+            duration,
+            intensity,
+        } => {
+            assert_eq!(gamepad, GAMEPAD);
+            Some((intensity, duration))
+        }
+        GamepadRumbleRequest::Stop { .. } => panic!("feedback should never stop the rumble"),
+    }
+}
+
+#[test]
+fn a_catch_is_a_short_light_buzz() {
+    assert_eq!(
+        rumble(FeedbackKind::Catch, 1.0),
+        Some((
+            GamepadRumbleIntensity::weak_motor(0.4),
+            Duration::from_millis(100)
+        ))
+    );
+}
+
+#[test]
+fn a_hit_is_a_long_full_rumble() {
+    assert_eq!(
+        rumble(FeedbackKind::Hit, 1.0),
+        Some((GamepadRumbleIntensity::MAX, Duration::from_millis(300)))
+    );
+}
+
+#[test]
+fn a_near_miss_buzzes_harder_than_a_catch() {
+    assert_eq!(
+        rumble(FeedbackKind::NearMiss, 1.0),
+        Some((
+            GamepadRumbleIntensity::weak_motor(0.6),
+            Duration::from_millis(150)
+        ))
+    );
+}
+
+#[test]
+fn go_rumbles_both_motors() {
+    assert_eq!(
+        rumble(FeedbackKind::CountdownGo, 1.0),
+        Some((
+            GamepadRumbleIntensity {
+                strong_motor: 0.5,
+                weak_motor: 0.5,
+            },
+            Duration::from_millis(200)
+        ))
+    );
+}
+
+#[test]
+fn the_rumble_setting_scales_the_motors_but_not_the_duration() {
+    assert_eq!(
+        rumble(FeedbackKind::Hit, 0.5),
+        Some((
+            GamepadRumbleIntensity {
+                strong_motor: 0.5,
+                weak_motor: 0.5,
+            },
+            Duration::from_millis(300)
+        ))
+    );
+}
+
+#[test]
+fn turned_off_rumble_sends_nothing() {
+    for kind in [
+        FeedbackKind::Catch,
+        FeedbackKind::Hit,
+        FeedbackKind::NearMiss,
+        FeedbackKind::CountdownGo,
+    ] {
+        assert_eq!(rumble(kind, 0.0), None);
+    }
+}
@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use birdstomp::{
+    bot::BotSkill,
+    gym::{AgentAction, GymEnv, ResetOptions},
+};
+
+#[test]
+fn reset_and_step_report_the_match() {
+    let mut env = GymEnv::new();
+    let observation = env.reset(ResetOptions {
+        seed: 3,
+        agents: 1,
+        bots: vec![BotSkill::Easy],
+        ..default()
+    });
+    assert!(observation.running);
+    assert_eq!(observation.players.len(), 2);
+    assert_eq!(observation.players[0].agent, Some(0));
+    assert_eq!(observation.players[1].agent, None);
+    assert!(observation.enemy.is_some());
+
+    let start = observation.players[0].position;
+    let mut result = None;
+    for _ in 0..32 {
+        result = Some(
+            env.step(&[AgentAction {
+                movement: Vec2::X,
+                ..default()
+            }])
+            .unwrap(),
+        );
+    }
+    let result = result.unwrap();
+    assert_eq!(result.reward.len(), 1);
+    assert!(!result.done);
+    assert!(result.observation.players[0].position.x > start.x);
+
+    assert!(env.step(&[]).is_err());
+}
@@ -0,0 +1,905 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::{
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
+    prelude::*,
+};
+use bevy_rapier2d::prelude::Velocity;
+use birdstomp::{
+    ball::Ball,
+    bot::{Bot, BotSettings, BotSkill},
+    call_for_pass::CallingForPass,
+    direction_indicator::DirectionIndicator,
+    enemy::Enemy,
+    feedback::RumbleIntensity,
+    gamepad::{AwaitingReconnect, PlayerAction},
+    grab::Grab,
+    headless,
+    lobby::{LobbyCountdown, LobbySettings, LOBBY_COUNTDOWN_SECONDS},
+    match_setup::MatchConfig,
+    player::{Player, STARTING_LIVES},
+    replay::ReplayPlayback,
+    resources::{CountdownTimer, InputSource, JoinedPlayers, KeyboardLayout, MatchSeed, PauseMenu},
+    revive::Downed,
+    rounds::{Contender, MatchRules, MatchScore, RoundClock, RoundMode},
+    shield::Shield,
+    stats::MatchStats,
+    tuning::{GrabTuning, ShieldTuning, Tuning},
+    AppState, BirdstompSettings, GameState,
+};
+use common::TestGame;
+
+// The ball starts in the middle of the arena, the players below it
+const BALL_START: Vec2 = Vec2::ZERO;
+
+// Walks `player` onto the loose ball and waits for the catch
+fn catch_ball(game: &mut TestGame, player: Entity) {
+    let position = game
+        .app
+        .world()
+        .get::<Transform>(player)
+        .unwrap()
+        .translation
+        .truncate();
+    game.hold_axis(
+        player,
+        PlayerAction::Move,
+        (BALL_START - position).normalize(),
+    );
+
+    let caught = game.tick_until(5.0, |world| world.get::<Player>(player).unwrap().have_ball);
+    assert!(caught, "the player never caught the ball");
+    game.release(player, PlayerAction::Move);
+    game.tick();
+}
+
+#[test]
+fn keyboard_players_join_with_their_join_keys() {
+    let mut game = TestGame::new();
+
+    game.press_key(KeyCode::Enter);
+    game.tick();
+    game.release_key(KeyCode::Enter);
+
+    // The left half of the keyboard moves with WASD as well, so it can't join next to keyboard + mouse
+    game.press_key(KeyCode::KeyQ);
+    game.press_key(KeyCode::KeyE);
+    game.tick();
+    game.release_key(KeyCode::KeyQ);
+    game.release_key(KeyCode::KeyE);
+
+    game.press_key(KeyCode::ShiftRight);
+    game.press_key(KeyCode::ControlRight);
+    game.tick();
+    game.tick();
+
+    let world = game.app.world_mut();
+    let mut players: Vec<(usize, InputSource)> = world
+        .query::<&Player>()
+        .iter(world)
+        .map(|player| (player.player_id, player.input_source))
+        .collect();
+    players.sort_by_key(|(player_id, _)| *player_id);
+    assert_eq!(
+        players,
+        vec![
+            (0, InputSource::KeyboardMouse),
+            (1, InputSource::Keyboard(KeyboardLayout::Right)),
+        ]
+    );
+    assert_eq!(world.resource::<JoinedPlayers>().0.len(), 2);
+}
+
+#[test]
+fn the_seed_from_the_settings_is_used_for_every_match() {
+    let app = headless::app(BirdstompSettings {
+        replays: false,
+        seed: Some(42),
+        ..BirdstompSettings::headless()
+    });
+
+    assert_eq!(app.world().resource::<MatchSeed>().0, 42);
+}
+
+#[test]
+fn countdown_starts_the_match() {
+    let mut game = TestGame::new();
+    game.spawn_player(0);
+
+    game.start_match();
+    assert_eq!(game.app_state(), AppState::InGame);
+    assert_eq!(game.game_state(), GameState::Paused);
+
+    game.tick_seconds(0.5);
+    assert_eq!(game.game_state(), GameState::Paused);
+
+    let running = game.tick_until(5.0, |world| {
+        world.resource::<State<GameState>>().get() == &GameState::Running
+    });
+    assert!(running, "the countdown never finished");
+}
+
+// A player and a bot in the lobby, with the player not ready yet
+fn lobby_with_a_bot() -> (TestGame, Entity) {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.spawn_bot(BotSkill::Easy);
+    // Seats both, the player has nothing held that could count as a ready press
+    game.tick();
+    (game, player)
+}
+
+#[test]
+fn the_match_starts_once_everyone_has_been_ready_for_the_countdown() {
+    let (mut game, player) = lobby_with_a_bot();
+    game.tick_seconds(1.0);
+    assert!(!game.app.world().contains_resource::<LobbyCountdown>());
+
+    game.tap(player, PlayerAction::Dash);
+    assert!(game.app.world().contains_resource::<LobbyCountdown>());
+
+    game.tick_seconds(LOBBY_COUNTDOWN_SECONDS - 0.25);
+    assert_eq!(game.app_state(), AppState::Menu);
+
+    let started = game.tick_until(0.5, |world| {
+        world.resource::<State<AppState>>().get() == &AppState::InGame
+    });
+    assert!(started, "the match never started");
+    assert!(!game.app.world().contains_resource::<LobbyCountdown>());
+}
+
+#[test]
+fn getting_unready_cancels_the_lobby_countdown() {
+    let (mut game, player) = lobby_with_a_bot();
+    game.tap(player, PlayerAction::Dash);
+    game.tick_seconds(1.0);
+
+    game.tap(player, PlayerAction::Dash);
+    assert!(!game.app.world().contains_resource::<LobbyCountdown>());
+
+    game.tick_seconds(LOBBY_COUNTDOWN_SECONDS);
+    assert_eq!(game.app_state(), AppState::Menu);
+}
+
+#[test]
+fn a_new_player_joining_cancels_the_lobby_countdown() {
+    let (mut game, player) = lobby_with_a_bot();
+    game.tap(player, PlayerAction::Dash);
+    game.tick_seconds(1.0);
+
+    let latecomer = game.spawn_player(2);
+    game.tick();
+    assert!(!game.app.world().contains_resource::<LobbyCountdown>());
+
+    game.tick_seconds(LOBBY_COUNTDOWN_SECONDS);
+    assert_eq!(game.app_state(), AppState::Menu);
+
+    // The countdown starts over once the newcomer is ready too
+    game.tap(latecomer, PlayerAction::Dash);
+    game.tick_seconds(LOBBY_COUNTDOWN_SECONDS - 0.25);
+    assert_eq!(game.app_state(), AppState::Menu);
+    let started = game.tick_until(0.5, |world| {
+        world.resource::<State<AppState>>().get() == &AppState::InGame
+    });
+    assert!(started, "the match never started");
+}
+
+#[test]
+fn players_add_and_remove_bots_from_the_lobby() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.tick();
+
+    game.tap(player, PlayerAction::Grab);
+    assert_eq!(
+        game.app.world().resource::<LobbySettings>().editor,
+        Some(player)
+    );
+
+    game.tap(player, PlayerAction::Right);
+    game.tap(player, PlayerAction::Right);
+    assert_eq!(game.count::<Bot>(), 2);
+    game.tap(player, PlayerAction::Left);
+    assert_eq!(game.count::<Bot>(), 1);
+
+    game.tap(player, PlayerAction::Down);
+    game.tap(player, PlayerAction::Right);
+    assert_eq!(
+        game.app.world().resource::<BotSettings>().skill,
+        BotSkill::Hard
+    );
+    let world = game.app.world_mut();
+    let skills: Vec<BotSkill> = world
+        .query::<&Bot>()
+        .iter(world)
+        .map(|bot| bot.skill)
+        .collect();
+    assert_eq!(skills, vec![BotSkill::Hard]);
+
+    // Back to picking a color, the bot doesn't change
+    game.tap(player, PlayerAction::Grab);
+    game.tap(player, PlayerAction::Right);
+    assert_eq!(game.count::<Bot>(), 1);
+    assert_eq!(game.app.world().resource::<LobbySettings>().editor, None);
+}
+
+#[test]
+fn players_change_the_rules_from_the_lobby() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.tick();
+    game.tap(player, PlayerAction::Grab);
+
+    // Past the bot settings
+    game.tap(player, PlayerAction::Down);
+    game.tap(player, PlayerAction::Down);
+    game.tap(player, PlayerAction::Right);
+    game.tap(player, PlayerAction::Right);
+    game.tap(player, PlayerAction::Left);
+    game.tap(player, PlayerAction::Down);
+    game.tap(player, PlayerAction::Left);
+    game.tap(player, PlayerAction::Down);
+    game.tap(player, PlayerAction::Right);
+
+    assert_eq!(
+        game.app.world().resource::<MatchConfig>().rules,
+        MatchRules {
+            mode: RoundMode::FreeForAll,
+            rounds_to_win: 2,
+            round_seconds: Some(120.0),
+        }
+    );
+}
+
+#[test]
+fn walking_into_the_ball_catches_it() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+
+    catch_ball(&mut game, player);
+
+    assert!(game.player(player).have_ball);
+    assert_eq!(game.count::<Ball>(), 0);
+    assert_eq!(game.count::<DirectionIndicator>(), 1);
+}
+
+#[test]
+fn throwing_releases_a_moving_ball() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+    catch_ball(&mut game, player);
+
+    game.press(player, PlayerAction::Throw);
+    game.tick();
+    game.release(player, PlayerAction::Throw);
+    game.tick();
+
+    assert!(!game.player(player).have_ball);
+    assert_eq!(game.count::<DirectionIndicator>(), 0);
+    let world = game.app.world_mut();
+    let velocity = world
+        .query_filtered::<&Velocity, With<Ball>>()
+        .single(world);
+    assert!(velocity.linvel.length() > 0.0);
+}
+
+// The first player has the ball, the second stands next to them. Grabs never
+// fumble, and the enemy starts far enough away to stay out of it
+fn carrier_and_thief() -> (TestGame, Entity, Entity) {
+    let mut game = TestGame::with_tuning(Tuning {
+        grab: GrabTuning {
+            fumble_chance: 0.0,
+            ..default()
+        },
+        ..default()
+    });
+    let carrier = game.spawn_player(0);
+    game.tick();
+    let thief = game.spawn_player(1);
+    game.start_running_match();
+    catch_ball(&mut game, carrier);
+
+    game.teleport(carrier, Vec2::new(-200.0, -200.0));
+    game.teleport(thief, Vec2::new(-180.0, -200.0));
+    let enemy = game.enemy();
+    game.teleport(enemy, Vec2::new(500.0, 300.0));
+    (game, carrier, thief)
+}
+
+#[test]
+fn grabbing_a_carrier_in_range_steals_the_ball() {
+    let (mut game, carrier, thief) = carrier_and_thief();
+
+    game.tap(thief, PlayerAction::Grab);
+    let stolen = game.tick_until(1.0, |world| world.get::<Player>(thief).unwrap().have_ball);
+    assert!(stolen, "the grab never took the ball");
+
+    assert!(!game.player(carrier).have_ball);
+    let stats = game.app.world().resource::<MatchStats>();
+    assert_eq!(stats.players[&1].steals, 1);
+    assert_eq!(game.player(thief).score, GrabTuning::default().steal_bonus);
+}
+
+#[test]
+fn grabbing_again_before_the_cooldown_does_nothing() {
+    let (mut game, carrier, thief) = carrier_and_thief();
+    // A grab that misses still starts the cooldown
+    game.teleport(thief, Vec2::new(200.0, -200.0));
+    game.tap(thief, PlayerAction::Grab);
+    game.tick_seconds(GrabTuning::default().wind_up);
+    assert!(!game.app.world().get::<Grab>(thief).unwrap().is_winding_up());
+
+    game.teleport(thief, Vec2::new(-180.0, -200.0));
+    game.tap(thief, PlayerAction::Grab);
+    game.tick_seconds(GrabTuning::default().wind_up * 2.0);
+
+    assert!(game.player(carrier).have_ball);
+    assert!(!game.player(thief).have_ball);
+    assert!(!game.app.world().get::<Grab>(thief).unwrap().is_winding_up());
+}
+
+#[test]
+fn go_rumbles_every_gamepad_unless_rumble_is_off() {
+    let mut game = TestGame::new();
+    let first = game.spawn_player(0);
+    game.spawn_player(1);
+    game.app
+        .world_mut()
+        .entity_mut(first)
+        .insert(RumbleIntensity(0.0));
+    game.start_running_match();
+
+    let rumbled: Vec<Gamepad> = game
+        .take_rumble_requests()
+        .into_iter()
+        .map(|request| match request {
+            GamepadRumbleRequest::Add {
+                gamepad,
+                duration,
+                intensity,
+            } => {
+                assert_eq!(duration, Duration::from_millis(200));
+                assert_eq!(intensity.strong_motor, 0.5);
+                gamepad
+            }
+            GamepadRumbleRequest::Stop { gamepad } => panic!("{gamepad:?} was told to stop"),
+        })
+        .collect();
+    assert_eq!(rumbled, vec![Gamepad::new(1)]);
+}
+
+#[test]
+fn catching_the_ball_rumbles_the_catchers_gamepad() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.spawn_player(1);
+    game.start_running_match();
+    game.take_rumble_requests();
+
+    catch_ball(&mut game, player);
+
+    let requests = game.take_rumble_requests();
+    assert_eq!(requests.len(), 1);
+    assert!(matches!(
+        requests[0],
+        GamepadRumbleRequest::Add {
+            gamepad,
+            intensity,
+            ..
+        } if gamepad == Gamepad::new(0) && intensity == GamepadRumbleIntensity::weak_motor(0.4)
+    ));
+}
+
+#[test]
+fn enemy_contact_costs_a_life_and_restarts_the_countdown() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+    // The enemy only chases whoever has the ball
+    catch_ball(&mut game, player);
+
+    let hit = game.tick_until(10.0, |world| {
+        world.get::<Player>(player).unwrap().lives < STARTING_LIVES
+    });
+    assert!(hit, "the enemy never reached the player");
+
+    assert_eq!(game.player(player).lives, STARTING_LIVES - 1);
+    game.tick();
+    assert_eq!(game.game_state(), GameState::Paused);
+    assert!(game.app.world().resource::<CountdownTimer>().duration > 1);
+}
+
+#[test]
+fn a_hit_costs_one_life_when_a_frame_runs_several_ticks() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+    let enemy = game.enemy();
+    game.teleport(player, Vec2::new(0.0, -100.0));
+    game.teleport(enemy, Vec2::new(0.0, -100.0));
+
+    game.tick_in_one_frame(8);
+
+    assert_eq!(game.player(player).lives, STARTING_LIVES - 1);
+    assert_eq!(game.game_state(), GameState::Paused);
+    let stats = game.app.world().resource::<MatchStats>();
+    assert_eq!(stats.players[&0].lives_lost, 1);
+    let enemy_position = game
+        .app
+        .world()
+        .get::<Transform>(enemy)
+        .unwrap()
+        .translation;
+    assert!(enemy_position.truncate().distance(Vec2::new(0.0, -100.0)) > 100.0);
+
+    // Play picks up again without the enemy still touching the player
+    let running = game.tick_until(10.0, |world| {
+        world.resource::<State<GameState>>().get() == &GameState::Running
+    });
+    assert!(running, "the countdown never finished");
+    game.tick();
+    assert_eq!(game.player(player).lives, STARTING_LIVES - 1);
+}
+
+#[test]
+fn losing_the_last_life_ends_the_match() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+    game.player_mut(player).lives = 1;
+    catch_ball(&mut game, player);
+
+    let game_over = game.tick_until(20.0, |world| {
+        world.resource::<State<AppState>>().get() == &AppState::GameOver
+    });
+    assert!(game_over, "the match never ended");
+
+    assert_eq!(game.player(player).lives, 0);
+    assert!(game.app.world().get::<Downed>(player).is_some());
+    let stats = game.app.world().resource::<MatchStats>();
+    assert_eq!(stats.players[&0].lives_lost, 1);
+    assert!(stats.players[&0].survival_seconds > 0.0);
+}
+
+#[test]
+fn a_carrier_who_goes_down_drops_the_ball() {
+    let mut game = TestGame::new();
+    let carrier = game.spawn_player(0);
+    game.spawn_player(1);
+    game.start_running_match();
+    catch_ball(&mut game, carrier);
+    game.player_mut(carrier).lives = 1;
+    let enemy = game.enemy();
+    game.teleport(carrier, Vec2::new(0.0, -100.0));
+    game.teleport(enemy, Vec2::new(0.0, -100.0));
+
+    let downed = game.tick_until(1.0, |world| world.get::<Downed>(carrier).is_some());
+    assert!(downed, "the enemy never downed the carrier");
+    game.tick();
+
+    assert!(!game.player(carrier).have_ball);
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.count::<DirectionIndicator>(), 0);
+    let stats = game.app.world().resource::<MatchStats>();
+    assert_eq!(stats.players[&0].lives_lost, 1);
+
+    // Nothing left to throw, and the dropped ball doesn't go back to them
+    game.press(carrier, PlayerAction::Throw);
+    game.tick_seconds(0.5);
+    assert!(!game.player(carrier).have_ball);
+    assert!(game.app.world().get::<Downed>(carrier).is_some());
+}
+
+#[test]
+fn a_raised_shield_absorbs_a_hit_and_starts_the_cooldown() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+    game.teleport(player, Vec2::new(0.0, -100.0));
+    game.press(player, PlayerAction::Shield);
+    game.tick();
+    assert!(game.app.world().get::<Shield>(player).unwrap().is_raised());
+
+    let enemy = game.enemy();
+    game.teleport(enemy, Vec2::new(0.0, -100.0));
+    let blocked = game.tick_until(ShieldTuning::default().block_window, |world| {
+        world
+            .resource::<MatchStats>()
+            .players
+            .get(&0)
+            .is_some_and(|stats| stats.blocks > 0)
+    });
+    assert!(blocked, "the shield never blocked the enemy");
+
+    assert_eq!(game.player(player).lives, STARTING_LIVES);
+    assert_eq!(game.game_state(), GameState::Running);
+    assert!(!game.app.world().get::<Shield>(player).unwrap().is_raised());
+    let enemy_position = game
+        .app
+        .world()
+        .get::<Transform>(enemy)
+        .unwrap()
+        .translation;
+    assert!(enemy_position.truncate().distance(Vec2::new(0.0, -100.0)) > 50.0);
+
+    // It can't go straight back up
+    game.release(player, PlayerAction::Shield);
+    game.tick();
+    game.press(player, PlayerAction::Shield);
+    game.tick();
+    assert!(!game.app.world().get::<Shield>(player).unwrap().is_raised());
+}
+
+#[test]
+fn downed_players_cannot_raise_their_shield() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.spawn_player(1);
+    game.start_running_match();
+    game.app
+        .world_mut()
+        .entity_mut(player)
+        .insert(Downed::new(60.0));
+
+    game.press(player, PlayerAction::Shield);
+    game.tick();
+
+    assert!(!game.app.world().get::<Shield>(player).unwrap().is_raised());
+}
+
+#[test]
+fn a_rematch_puts_the_same_players_back_on_their_feet() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+    game.player_mut(player).lives = 1;
+    catch_ball(&mut game, player);
+    let game_over = game.tick_until(20.0, |world| {
+        world.resource::<State<AppState>>().get() == &AppState::GameOver
+    });
+    assert!(game_over, "the match never ended");
+
+    game.start_running_match();
+
+    assert_eq!(game.player(player).lives, STARTING_LIVES);
+    assert!(!game.player(player).have_ball);
+    assert!(game.app.world().get::<Downed>(player).is_none());
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.app.world().resource::<MatchScore>().round, 1);
+}
+
+#[test]
+fn quitting_to_the_menu_clears_the_arena_for_the_next_match() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    assert_eq!(game.count::<Enemy>(), 0);
+    game.start_running_match();
+    catch_ball(&mut game, player);
+    let hit = game.tick_until(10.0, |world| {
+        world.get::<Player>(player).unwrap().lives < STARTING_LIVES
+    });
+    assert!(hit, "the enemy never reached the player");
+
+    game.app
+        .world_mut()
+        .insert_resource(NextState::Pending(AppState::Menu));
+    game.tick();
+    assert_eq!(game.count::<Enemy>(), 0);
+    assert_eq!(game.count::<Ball>(), 0);
+    assert_eq!(game.count::<DirectionIndicator>(), 0);
+    assert!(!game.player(player).have_ball);
+
+    game.start_running_match();
+    assert_eq!(game.count::<Enemy>(), 1);
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.player(player).lives, STARTING_LIVES);
+}
+
+#[test]
+fn a_recorded_match_plays_back_the_same_way() {
+    let mut game = TestGame::with_replays();
+    game.app.insert_resource(MatchConfig {
+        rules: MatchRules {
+            round_seconds: Some(30.0),
+            ..default()
+        },
+        starting_lives: 2,
+    });
+    let player = game.spawn_player(0);
+    game.start_running_match();
+    catch_ball(&mut game, player);
+    game.hold_axis(player, PlayerAction::Move, Vec2::new(1.0, 0.5).normalize());
+    game.tick_seconds(0.5);
+    game.press(player, PlayerAction::Throw);
+    game.release(player, PlayerAction::Move);
+    game.tick();
+    game.release(player, PlayerAction::Throw);
+    game.tick_seconds(4.0);
+
+    let replay = game.recorded_replay();
+    let stats = format!("{:?}", game.app.world().resource::<MatchStats>());
+    let position = game
+        .app
+        .world()
+        .get::<Transform>(player)
+        .unwrap()
+        .translation;
+    let lives = game.player(player).lives;
+
+    let mut playback = TestGame::playing_back(replay);
+    let finished = playback.tick_until(20.0, |world| {
+        world.resource::<ReplayPlayback>().is_finished()
+    });
+    assert!(finished, "the replay never finished");
+
+    let world = playback.app.world_mut();
+    let (played_back_lives, played_back_position) = world
+        .query::<(&Player, &Transform)>()
+        .iter(world)
+        .find(|(player, _)| player.player_id == 0)
+        .map(|(player, transform)| (player.lives, transform.translation))
+        .expect("the recorded player never joined");
+    assert_eq!(played_back_lives, lives);
+    assert_eq!(played_back_position, position);
+    assert_eq!(
+        format!("{:?}", playback.app.world().resource::<MatchStats>()),
+        stats
+    );
+    assert_eq!(
+        playback
+            .app
+            .world()
+            .resource::<MatchConfig>()
+            .starting_lives,
+        2
+    );
+}
+
+// Downs the first player and keeps the second one next to them, the third
+// one keeps a free for all round going
+fn stand_next_to_a_downed_player(mode: RoundMode) -> (TestGame, Entity) {
+    let mut game = TestGame::new();
+    let downed = game.spawn_player(0);
+    let rescuer = game.spawn_player(1);
+    game.spawn_player(2);
+    game.app.insert_resource(MatchConfig {
+        rules: MatchRules { mode, ..default() },
+        ..default()
+    });
+    game.start_running_match();
+
+    game.teleport(downed, Vec2::new(-200.0, -200.0));
+    game.teleport(rescuer, Vec2::new(-170.0, -200.0));
+    game.player_mut(downed).lives = 0;
+    game.app
+        .world_mut()
+        .entity_mut(downed)
+        .insert(Downed::new(60.0));
+    game.tick_seconds(3.0);
+
+    (game, downed)
+}
+
+#[test]
+fn teammates_revive_a_downed_player_in_co_op() {
+    let (game, downed) = stand_next_to_a_downed_player(RoundMode::CoOp);
+
+    assert!(game.app.world().get::<Downed>(downed).is_none());
+    assert_eq!(
+        game.player(downed).lives,
+        game.app.world().resource::<Tuning>().revive.revived_lives
+    );
+}
+
+#[test]
+fn rivals_cannot_revive_a_downed_player_in_free_for_all() {
+    let (game, downed) = stand_next_to_a_downed_player(RoundMode::FreeForAll);
+
+    assert!(game.app.world().get::<Downed>(downed).is_some());
+}
+
+#[test]
+fn outlasting_a_timed_round_wins_it_and_starts_the_next() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.app.insert_resource(MatchConfig {
+        rules: MatchRules {
+            rounds_to_win: 2,
+            round_seconds: Some(2.0),
+            ..default()
+        },
+        ..default()
+    });
+    game.start_running_match();
+    game.player_mut(player).lives = 3;
+
+    let round_over = game.tick_until(5.0, |world| {
+        world.resource::<RoundClock>().in_intermission()
+    });
+    assert!(round_over, "the round never ran out of time");
+    game.tick();
+    let score = game.app.world().resource::<MatchScore>();
+    assert_eq!(score.wins(Contender::Players), 1);
+    assert_eq!(game.game_state(), GameState::Paused);
+
+    let next_round = game.tick_until(10.0, |world| {
+        world.resource::<State<GameState>>().get() == &GameState::Running
+    });
+    assert!(next_round, "the next round never started");
+    assert_eq!(game.app.world().resource::<MatchScore>().round, 2);
+    assert_eq!(game.player(player).lives, STARTING_LIVES);
+    assert_eq!(game.app_state(), AppState::InGame);
+}
+
+#[test]
+fn the_pause_menu_resumes_and_restarts_the_round_outside_the_intermission() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.app.insert_resource(MatchConfig {
+        rules: MatchRules {
+            rounds_to_win: 2,
+            round_seconds: Some(2.0),
+            ..default()
+        },
+        ..default()
+    });
+    game.start_running_match();
+
+    game.tap(player, PlayerAction::Start);
+    assert_eq!(game.app.world().resource::<PauseMenu>().player, player);
+    assert_eq!(game.game_state(), GameState::Paused);
+
+    // Start again resumes through the countdown
+    game.tap(player, PlayerAction::Start);
+    assert!(!game.app.world().contains_resource::<PauseMenu>());
+    let resumed = game.tick_until(5.0, |world| {
+        world.resource::<State<GameState>>().get() == &GameState::Running
+    });
+    assert!(resumed, "the match never resumed");
+
+    // Restart Round puts everyone back on their feet
+    game.player_mut(player).lives = 3;
+    game.tap(player, PlayerAction::Start);
+    game.tap(player, PlayerAction::Down);
+    game.tap(player, PlayerAction::Dash);
+    assert!(!game.app.world().contains_resource::<PauseMenu>());
+    assert_eq!(game.player(player).lives, STARTING_LIVES);
+
+    // The next round is already coming during the intermission, so it can't be restarted
+    let round_over = game.tick_until(10.0, |world| {
+        world.resource::<RoundClock>().in_intermission()
+    });
+    assert!(round_over, "the round never ran out of time");
+    game.player_mut(player).lives = 3;
+    game.tap(player, PlayerAction::Start);
+    game.tap(player, PlayerAction::Down);
+    game.tap(player, PlayerAction::Dash);
+    assert_eq!(game.app.world().resource::<PauseMenu>().selected, 1);
+    assert_eq!(game.player(player).lives, 3);
+    assert_eq!(game.app.world().resource::<MatchScore>().round, 1);
+}
+
+#[test]
+fn a_lost_controller_holds_the_match_until_it_is_back() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+
+    game.disconnect_gamepad(0);
+    game.tick();
+    assert!(game.app.world().get::<AwaitingReconnect>(player).is_some());
+    assert_eq!(game.game_state(), GameState::Paused);
+    // Nothing resumes the match while the controller is missing
+    game.tick_seconds(5.0);
+    assert_eq!(game.game_state(), GameState::Paused);
+
+    game.connect_gamepad(0);
+    game.tick();
+    assert!(game.app.world().get::<AwaitingReconnect>(player).is_none());
+    assert!(game.app.world().resource::<CountdownTimer>().duration > 1);
+    let resumed = game.tick_until(5.0, |world| {
+        world.resource::<State<GameState>>().get() == &GameState::Running
+    });
+    assert!(resumed, "the countdown never restarted");
+}
+
+#[test]
+fn bots_fetch_the_loose_ball_and_pass_it_on() {
+    let mut game = TestGame::new();
+    let first = game.spawn_bot(BotSkill::Hard);
+    game.tick();
+    let second = game.spawn_bot(BotSkill::Hard);
+    game.start_running_match();
+
+    let caught = game.tick_until(10.0, |world| {
+        [first, second]
+            .iter()
+            .any(|bot| world.get::<Player>(*bot).unwrap().have_ball)
+    });
+    assert!(caught, "no bot went for the ball");
+
+    let passed = game.tick_until(5.0, |world| {
+        [first, second]
+            .iter()
+            .all(|bot| !world.get::<Player>(*bot).unwrap().have_ball)
+    });
+    assert!(passed, "the bot never threw the ball");
+}
+
+#[test]
+fn calling_for_the_ball_lasts_until_it_runs_out() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.start_running_match();
+
+    game.tap(player, PlayerAction::CallForPass);
+    assert!(game.app.world().get::<CallingForPass>(player).is_some());
+
+    let expired = game.tick_until(5.0, |world| world.get::<CallingForPass>(player).is_none());
+    assert!(expired, "the call never ran out");
+}
+
+#[test]
+fn bots_call_for_the_ball_while_a_teammate_has_it() {
+    let mut game = TestGame::new();
+    let player = game.spawn_player(0);
+    game.tick();
+    let bot = game.spawn_bot(BotSkill::Hard);
+    game.start_running_match();
+    catch_ball(&mut game, player);
+
+    let called = game.tick_until(2.0, |world| world.get::<CallingForPass>(bot).is_some());
+    assert!(called, "the bot never called for the ball");
+}
+
+#[test]
+fn bots_keep_the_ball_from_rivals_in_free_for_all() {
+    let mut game = TestGame::new();
+    game.app.insert_resource(MatchConfig {
+        rules: MatchRules {
+            mode: RoundMode::FreeForAll,
+            ..default()
+        },
+        ..default()
+    });
+    let bot = game.spawn_bot(BotSkill::Hard);
+    game.tick();
+    let rival = game.spawn_player(1);
+    game.start_running_match();
+
+    game.teleport(rival, Vec2::new(-300.0, -200.0));
+    game.teleport(bot, BALL_START);
+    let caught = game.tick_until(1.0, |world| world.get::<Player>(bot).unwrap().have_ball);
+    assert!(caught, "the bot never caught the ball");
+
+    game.tap(rival, PlayerAction::CallForPass);
+    let passed = game.tick_until(3.0, |world| world.get::<Player>(rival).unwrap().have_ball);
+    assert!(!passed, "the bot passed to a rival");
+    assert!(game.app.world().get::<CallingForPass>(bot).is_none());
+}
+
+#[test]
+fn bots_pass_to_the_teammate_calling_for_the_ball() {
+    let mut game = TestGame::new();
+    let bot = game.spawn_bot(BotSkill::Hard);
+    game.tick();
+    let caller = game.spawn_player(1);
+    game.tick();
+    let other = game.spawn_player(2);
+    game.start_running_match();
+
+    game.teleport(caller, Vec2::new(-300.0, -200.0));
+    game.teleport(other, Vec2::new(300.0, -200.0));
+    game.teleport(bot, BALL_START);
+    let caught = game.tick_until(1.0, |world| world.get::<Player>(bot).unwrap().have_ball);
+    assert!(caught, "the bot never caught the ball");
+
+    game.tap(caller, PlayerAction::CallForPass);
+    let passed = game.tick_until(3.0, |world| world.get::<Player>(caller).unwrap().have_ball);
+    assert!(passed, "the bot never passed to the caller");
+    assert!(!game.player(other).have_ball);
+}